@@ -0,0 +1,294 @@
+//! Motion profile and feedforward for the lady brown arm.
+//!
+//! Kept out of the robot crate, which only builds for the brain, so the tests
+//! run on the host:
+//!
+//! ```sh
+//! cargo test -p lady-brown-profile --target x86_64-unknown-linux-gnu
+//! ```
+
+#![cfg_attr(not(test), no_std)]
+
+use libm::{cos, floor, sqrt};
+
+/// Profile constraints the lady brown moves with.
+pub const DEFAULT_CONSTRAINTS: ProfileConstraints = ProfileConstraints {
+    max_velocity: 400.0,
+    max_acceleration: 1500.0,
+};
+/// Feedforward model of the lady brown.
+pub const FEEDFORWARD: ArmFeedforward = ArmFeedforward {
+    ks: 0.3,
+    kg: 1.2,
+    kv: 0.01,
+    ka: 0.0005,
+    horizontal_angle: 90.0,
+};
+
+/// Velocity and acceleration limits for [`MotionProfile`], in degrees of arm
+/// rotation per second (squared).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProfileConstraints {
+    pub max_velocity: f64,
+    pub max_acceleration: f64,
+}
+
+/// A point along a motion profile.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ProfileState {
+    /// Arm angle in degrees.
+    pub position: f64,
+    /// Arm velocity in degrees per second.
+    pub velocity: f64,
+    /// Arm acceleration in degrees per second squared.
+    pub acceleration: f64,
+}
+
+/// Trapezoidal motion profile that is advanced one step at a time, so the goal
+/// can change mid-motion without discontinuities in the setpoint.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MotionProfile {
+    constraints: ProfileConstraints,
+    setpoint: ProfileState,
+}
+
+impl MotionProfile {
+    pub fn new(constraints: ProfileConstraints, position: f64) -> Self {
+        Self {
+            constraints,
+            setpoint: ProfileState {
+                position,
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn set_constraints(&mut self, constraints: ProfileConstraints) {
+        self.constraints = constraints;
+    }
+
+    /// Jumps to `position` at rest, e.g. after the encoder has been re-zeroed.
+    pub fn reset(&mut self, position: f64) {
+        self.setpoint = ProfileState {
+            position,
+            ..Default::default()
+        };
+    }
+
+    /// Advances the profile by `dt` seconds towards `goal` and returns the new
+    /// setpoint.
+    pub fn step(&mut self, goal: f64, dt: f64) -> ProfileState {
+        let ProfileConstraints {
+            max_velocity,
+            max_acceleration,
+        } = self.constraints;
+        let error = goal - self.setpoint.position;
+        let max_step = max_acceleration * dt;
+        // Fastest velocity from which we can still stop at the goal. Moving at
+        // `v` and then slowing by `max_step` for `n` more steps covers
+        // `dt * ((n + 1) * v - max_step * n * (n + 1) / 2)`, so find `n` and
+        // solve for `v`. The last step then stops from below `max_step`.
+        let steps = floor((sqrt(1.0 + 8.0 * error.abs() / (max_step * dt)) - 1.0) / 2.0);
+        let stopping_velocity =
+            (error.abs() / dt + max_step * steps * (steps + 1.0) / 2.0) / (steps + 1.0);
+        let target_velocity = error.signum() * stopping_velocity.min(max_velocity);
+
+        let velocity = self.setpoint.velocity
+            + (target_velocity - self.setpoint.velocity).clamp(-max_step, max_step);
+        let position = self.setpoint.position + velocity * dt;
+
+        let arrived = (goal - position).signum() != error.signum() || error == 0.0;
+        if arrived && self.setpoint.velocity.abs() <= max_step {
+            // Close enough to stop within one step
+            self.setpoint = ProfileState {
+                position: goal,
+                ..Default::default()
+            };
+        } else {
+            self.setpoint = ProfileState {
+                position,
+                velocity,
+                acceleration: (velocity - self.setpoint.velocity) / dt,
+            };
+        }
+        self.setpoint
+    }
+
+    /// Returns `true` once the setpoint has reached `goal` and stopped.
+    pub fn is_finished(&self, goal: f64) -> bool {
+        self.setpoint.position == goal && self.setpoint.velocity == 0.0
+    }
+}
+
+/// Feedforward model for a rotating arm.
+///
+/// `kg` is the voltage needed to hold the arm horizontal; it is scaled by the
+/// cosine of the arm's angle from horizontal to compensate for gravity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ArmFeedforward {
+    pub ks: f64,
+    pub kg: f64,
+    pub kv: f64,
+    pub ka: f64,
+    /// Arm angle, in degrees, at which the arm is horizontal.
+    pub horizontal_angle: f64,
+}
+
+impl ArmFeedforward {
+    /// Returns the feedforward voltage for the given profile setpoint.
+    pub fn calculate(&self, setpoint: ProfileState) -> f64 {
+        let static_friction = if setpoint.velocity == 0.0 {
+            0.0
+        } else {
+            self.ks * setpoint.velocity.signum()
+        };
+        static_friction
+            + self.kg * cos((setpoint.position - self.horizontal_angle).to_radians())
+            + self.kv * setpoint.velocity
+            + self.ka * setpoint.acceleration
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONSTRAINTS: ProfileConstraints = DEFAULT_CONSTRAINTS;
+    const DT: f64 = 0.01;
+    /// Physics steps per profile step.
+    const SUBSTEPS: u32 = 10;
+
+    /// An arm that behaves exactly like [`FEEDFORWARD`] models it, so it
+    /// should follow the profile with feedforward alone.
+    struct Arm {
+        position: f64,
+        velocity: f64,
+    }
+
+    impl Arm {
+        fn step(&mut self, voltage: f64, dt: f64) {
+            let gravity = FEEDFORWARD.kg
+                * (self.position - FEEDFORWARD.horizontal_angle)
+                    .to_radians()
+                    .cos();
+            let drive = voltage - gravity - FEEDFORWARD.kv * self.velocity;
+            if self.velocity == 0.0 && drive.abs() <= FEEDFORWARD.ks {
+                // Static friction holds the arm
+                return;
+            }
+            let direction = if self.velocity == 0.0 {
+                drive.signum()
+            } else {
+                self.velocity.signum()
+            };
+            let acceleration = (drive - FEEDFORWARD.ks * direction) / FEEDFORWARD.ka;
+            let velocity = self.velocity + acceleration * dt;
+            if self.velocity != 0.0
+                && velocity.signum() != direction
+                && drive.abs() <= FEEDFORWARD.ks
+            {
+                // Friction stopped the arm within this step
+                self.velocity = 0.0;
+            } else {
+                self.velocity = velocity;
+            }
+            self.position += self.velocity * dt;
+        }
+    }
+
+    fn run(profile: &mut MotionProfile, goal: f64, steps: usize) -> Vec<ProfileState> {
+        (0..steps).map(|_| profile.step(goal, DT)).collect()
+    }
+
+    #[test]
+    fn respects_constraints() {
+        let mut profile = MotionProfile::new(CONSTRAINTS, 10.0);
+        let mut setpoints = run(&mut profile, 160.0, 30);
+        // Reverse mid-motion, then overshoot a goal that's too close to stop at
+        setpoints.extend(run(&mut profile, 40.0, 200));
+        setpoints.extend(run(&mut profile, 160.0, 50));
+        setpoints.extend(run(&mut profile, 120.0, 200));
+        assert!(profile.is_finished(120.0));
+        let mut previous = ProfileState {
+            position: 10.0,
+            ..Default::default()
+        };
+        for setpoint in setpoints {
+            assert!(setpoint.velocity.abs() <= CONSTRAINTS.max_velocity + 1e-9);
+            assert!(
+                (setpoint.velocity - previous.velocity).abs()
+                    <= CONSTRAINTS.max_acceleration * DT + 1e-9
+            );
+            previous = setpoint;
+        }
+    }
+
+    #[test]
+    fn reaches_target() {
+        let mut profile = MotionProfile::new(CONSTRAINTS, 10.0);
+        let setpoints = run(&mut profile, 160.0, 200);
+        assert!(profile.is_finished(160.0));
+        // The first setpoint has to move, and it stays at the goal once there
+        assert!(setpoints[0].position > 10.0);
+        let arrived = setpoints.iter().position(|s| s.position == 160.0).unwrap();
+        assert!(setpoints[arrived..].iter().all(|s| s.position == 160.0));
+        // A trapezoidal profile needs at least the time to cover the distance
+        // at full speed plus the time to reach full speed, give or take a step
+        let minimum = 150.0 / CONSTRAINTS.max_velocity
+            + CONSTRAINTS.max_velocity / CONSTRAINTS.max_acceleration;
+        assert!((arrived + 1) as f64 * DT >= minimum - DT);
+    }
+
+    #[test]
+    fn feedforward_follows_profile() {
+        let mut profile = MotionProfile::new(CONSTRAINTS, 10.0);
+        let mut arm = Arm {
+            position: 10.0,
+            velocity: 0.0,
+        };
+        for _ in 0..200 {
+            let setpoint = profile.step(160.0, DT);
+            let voltage = FEEDFORWARD.calculate(setpoint);
+            for _ in 0..SUBSTEPS {
+                arm.step(voltage, DT / SUBSTEPS as f64);
+            }
+            assert!(
+                (arm.position - setpoint.position).abs() < 5.0,
+                "arm at {:.1} fell behind setpoint {:.1}",
+                arm.position,
+                setpoint.position
+            );
+        }
+        assert!((arm.position - 160.0).abs() < 2.0);
+        assert_eq!(arm.velocity, 0.0);
+    }
+
+    #[test]
+    fn gravity_compensation_holds_arm() {
+        for angle in [10.0, 45.0, 90.0, 135.0, 160.0] {
+            let mut arm = Arm {
+                position: angle,
+                velocity: 0.0,
+            };
+            let voltage = FEEDFORWARD.calculate(ProfileState {
+                position: angle,
+                ..Default::default()
+            });
+            for _ in 0..1000 {
+                arm.step(voltage, DT);
+            }
+            assert_eq!(arm.position, angle);
+        }
+        // Without compensation, gravity pulls the arm down near horizontal
+        for angle in [45.0, 90.0, 135.0] {
+            let mut arm = Arm {
+                position: angle,
+                velocity: 0.0,
+            };
+            for _ in 0..10 {
+                arm.step(0.0, DT);
+            }
+            assert!(arm.position < angle);
+        }
+    }
+}
@@ -1,6 +1,13 @@
-use core::fmt::Display;
+use alloc::boxed::Box;
+use core::{f64::consts::PI, fmt::Display, future::Future, pin::Pin};
+
+use crate::{
+    subsystems::{drivetrain_actions::TILES_TO_MM, intake::RingColor},
+    Robot,
+};
 
 pub mod alliance_four;
+pub mod corner;
 pub mod five;
 pub mod forward;
 pub mod four;
@@ -13,7 +20,6 @@ pub mod positive_rush;
 pub mod test;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-#[allow(unused)]
 pub enum AutonCategory {
     RedPositive,
     RedNegative,
@@ -23,6 +29,26 @@ pub enum AutonCategory {
     Test,
 }
 
+impl AutonCategory {
+    pub const ALL: [AutonCategory; 6] = [
+        AutonCategory::RedPositive,
+        AutonCategory::RedNegative,
+        AutonCategory::BluePositive,
+        AutonCategory::BlueNegative,
+        AutonCategory::Skills,
+        AutonCategory::Test,
+    ];
+
+    /// Returns the alliance the category's routes play for.
+    pub const fn alliance(&self) -> Option<RingColor> {
+        match self {
+            AutonCategory::RedPositive | AutonCategory::RedNegative => Some(RingColor::Red),
+            AutonCategory::BluePositive | AutonCategory::BlueNegative => Some(RingColor::Blue),
+            AutonCategory::Skills | AutonCategory::Test => None,
+        }
+    }
+}
+
 impl Display for AutonCategory {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
@@ -35,3 +61,128 @@ impl Display for AutonCategory {
         }
     }
 }
+
+/// Where a route starts and drives, drawn by the touchscreen selector.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RoutePreview {
+    /// Starting pose before reversing, in mm and radians.
+    pub start: (f64, f64, f64),
+    /// Points driven to before reversing, in tiles.
+    pub path: &'static [(f64, f64)],
+    /// Whether the route runs with reversed tracking, mirroring it across the
+    /// field.
+    pub reverse: bool,
+}
+
+impl RoutePreview {
+    const fn new(start: (f64, f64, f64), path: &'static [(f64, f64)], reverse: bool) -> Self {
+        Self {
+            start,
+            path,
+            reverse,
+        }
+    }
+
+    fn mirror(&self, x: f64) -> f64 {
+        if self.reverse {
+            -x
+        } else {
+            x
+        }
+    }
+
+    /// Returns the starting pose on the side of the field the route runs on,
+    /// in mm and radians.
+    pub fn start_pose(&self) -> (f64, f64, f64) {
+        let (x, y, heading) = self.start;
+        if self.reverse {
+            (-x, y, PI - heading)
+        } else {
+            (x, y, heading)
+        }
+    }
+
+    /// Returns the points the route drives to on the side of the field it
+    /// runs on, in mm.
+    pub fn path_points(&self) -> impl Iterator<Item = (f64, f64)> + '_ {
+        self.path
+            .iter()
+            .map(|&(x, y)| (self.mirror(x * TILES_TO_MM), y * TILES_TO_MM))
+    }
+}
+
+pub type RouteFn = for<'a> fn(&'a mut Robot) -> Pin<Box<dyn Future<Output = ()> + 'a>>;
+
+/// A route selectable on the brain's touchscreen.
+pub struct AutonRoute {
+    pub category: AutonCategory,
+    pub name: &'static str,
+    pub preview: Option<RoutePreview>,
+    pub run: RouteFn,
+}
+
+/// Lists every route once for both selectors, as `category, name, route,
+/// [preview]`, and passes the list to `$callback` to build its routes from.
+///
+/// The preview is the module holding the route's `START` and `PATH` and
+/// whether the route is mirrored, or empty for routes without one. Route
+/// paths are relative to this module.
+macro_rules! with_routes {
+    ($callback:ident) => {
+        $callback! {
+            Test, "Test red", test::red, [];
+            Test, "Test blue", test::blue, [];
+            Test, "Forward", forward::route, [];
+            Test, "Corner", corner::route, [];
+            RedNegative, "Rush", negative_rush::red, [negative_rush, false];
+            BlueNegative, "Rush", negative_rush::blue, [negative_rush, true];
+            RedNegative, "Middle", negative_middle::red, [negative_middle, false];
+            BlueNegative, "Middle", negative_middle::blue, [negative_middle, true];
+            RedNegative, "Safe", negative_safe::red, [negative_safe, false];
+            BlueNegative, "Safe", negative_safe::blue, [negative_safe, true];
+            RedPositive, "Rush", positive_rush::red, [positive_rush, false];
+            BluePositive, "Rush", positive_rush::blue, [positive_rush, true];
+            RedPositive, "AWP", positive_awp::red, [positive_awp, false];
+            BluePositive, "AWP", positive_awp::blue, [positive_awp, true];
+            RedPositive, "New", positive_new::red, [positive_new, false];
+            BluePositive, "New", positive_new::blue, [positive_new, true];
+            // All sides autons for elims
+            RedPositive, "Alliance 4*", alliance_four::red_positive, [alliance_four, false];
+            BluePositive, "Alliance 4*", alliance_four::blue_positive, [alliance_four, true];
+            RedNegative, "Alliance 4*", alliance_four::red_negative, [alliance_four, true];
+            BlueNegative, "Alliance 4*", alliance_four::blue_negative, [alliance_four, false];
+            RedPositive, "Four*", four::red_positive, [four, false];
+            BluePositive, "Four*", four::blue_positive, [four, true];
+            RedNegative, "Four*", four::red_negative, [four, true];
+            BlueNegative, "Four*", four::blue_negative, [four, false];
+            RedPositive, "Five*", five::red_positive, [five, false];
+            BluePositive, "Five*", five::blue_positive, [five, true];
+            RedNegative, "Five*", five::red_negative, [five, true];
+            BlueNegative, "Five*", five::blue_negative, [five, false];
+        }
+    };
+}
+pub(crate) use with_routes;
+
+macro_rules! route_preview {
+    () => {
+        None
+    };
+    ($module:ident, $reverse:literal) => {
+        Some(RoutePreview::new($module::START, $module::PATH, $reverse))
+    };
+}
+
+macro_rules! auton_routes {
+    ($($category:ident, $name:literal, $module:ident::$route:ident, [$($preview:tt)*];)*) => {
+        &[$(AutonRoute {
+            category: AutonCategory::$category,
+            name: $name,
+            preview: route_preview!($($preview)*),
+            run: |robot| Box::pin($module::$route(robot)),
+        }),*]
+    };
+}
+
+/// Routes shown by the touchscreen selector.
+pub static ROUTES: &[AutonRoute] = with_routes!(auton_routes);
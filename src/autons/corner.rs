@@ -0,0 +1,12 @@
+use crate::Robot;
+
+/// Clears rings out of the corner the robot is placed facing, to try the sweep
+/// without driving there.
+pub async fn route(robot: &mut Robot) {
+    robot.route_step.set("corner");
+    robot
+        .doinker
+        .clone()
+        .clear_corner(&mut robot.drivetrain)
+        .await;
+}
@@ -7,18 +7,26 @@ use crate::{
     Robot,
 };
 
+/// Starting pose (x and y in mm, heading in radians) on the unreversed side.
+pub const START: (f64, f64, f64) = (1.0 * TILES_TO_MM, -2.5 * TILES_TO_MM, -FRAC_PI_2);
+// Points the route drives to, in tiles
+const GOAL: (f64, f64) = (1.0, -0.8);
+const RING: (f64, f64) = (2.1, -1.0);
+const CORNER: (f64, f64) = (2.7, -2.7);
+const STAKE_RING: (f64, f64) = (0.1, -2.2);
+const MIDDLE: (f64, f64) = (2.0, -0.6);
+/// Points the route drives to in order, for the selector's field preview.
+pub const PATH: &[(f64, f64)] = &[GOAL, RING, CORNER, STAKE_RING, MIDDLE];
+
 async fn route(robot: &mut Robot) {
-    robot
-        .tracking
-        .borrow_mut()
-        .set_pose((1.0 * TILES_TO_MM, -2.5 * TILES_TO_MM, -FRAC_PI_2).into());
+    robot.tracking.borrow_mut().set_pose(START.into());
 
-    // stake
+    robot.route_step.set("goal");
     let mut clamp = robot.clamp.clone();
     robot
         .drivetrain
         .action(drivetrain_actions::drive_to_point(
-            (1.0, -0.8).into(),
+            GOAL.into(),
             true,
             CONFIG
                 .with_linear_error_tolerance(200.0)
@@ -26,18 +34,19 @@ async fn route(robot: &mut Robot) {
         ))
         .with_callback(move |pose| {
             if pose.y() > -1.1 * TILES_TO_MM {
-                clamp.extend();
+                clamp.grab_without_sensor();
             }
         })
         .await;
+    robot.clamp.grab().await;
     robot.intake.run(vexide::prelude::Direction::Forward);
     sleep(Duration::from_millis(200)).await;
 
-    // Get ring at (2.0, -1.0)
+    robot.route_step.set("ring");
     robot
         .drivetrain
         .action(drivetrain_actions::drive_to_point(
-            (2.1, -1.0).into(),
+            RING.into(),
             false,
             CONFIG
                 .with_linear_error_tolerance(200.0)
@@ -46,21 +55,25 @@ async fn route(robot: &mut Robot) {
         .await;
     sleep(Duration::from_millis(200)).await;
 
-    // Corner
+    robot.route_step.set("corner");
     let intake = robot.intake.clone();
     robot
         .drivetrain
         .action(drivetrain_actions::drive_to_point(
-            (2.7, -2.7).into(),
+            CORNER.into(),
             false,
             CONFIG.with_linear_error_tolerance(100.0),
         ))
         .with_callback(move |pose| {
             if pose.y() < -2.0 * TILES_TO_MM {
+                intake.set_speeds(crate::subsystems::intake::IntakeSpeeds::EJECT);
                 intake.run(vexide::prelude::Direction::Reverse);
             }
         })
         .await;
+    robot
+        .intake
+        .set_speeds(crate::subsystems::intake::IntakeSpeeds::MOBILE_GOAL);
     robot.intake.run(vexide::prelude::Direction::Forward);
     sleep(Duration::from_millis(400)).await;
     robot
@@ -92,14 +105,14 @@ async fn route(robot: &mut Robot) {
         .await;
     robot.intake.stop(); // to prevent flying ring
 
-    // Get the ring in front of alliance stake
+    robot.route_step.set("stake ring");
     let mut intake_raiser = robot.intake_raiser.clone();
     let intake = robot.intake.clone();
     let mut flag = false;
     robot
         .drivetrain
         .action(drivetrain_actions::drive_to_point(
-            (0.1, -2.2).into(),
+            STAKE_RING.into(),
             false,
             CONFIG
                 .with_linear_velocity_tolerance(200.0)
@@ -118,11 +131,11 @@ async fn route(robot: &mut Robot) {
         })
         .await;
 
-    // Drive to middle
+    robot.route_step.set("middle");
     robot
         .drivetrain
         .action(drivetrain_actions::boomerang_to_point(
-            (2.0, -0.6).into(),
+            MIDDLE.into(),
             CONFIG,
         ))
         .await;
@@ -7,18 +7,25 @@ use crate::{
     Robot,
 };
 
+/// Starting pose (x and y in mm, heading in radians) on the unreversed side.
+pub const START: (f64, f64, f64) = (1.0 * TILES_TO_MM, -2.5 * TILES_TO_MM, -FRAC_PI_2);
+// Points the route drives to, in tiles
+const GOAL: (f64, f64) = (1.0, -0.8);
+const RING: (f64, f64) = (2.1, -1.0);
+const CORNER: (f64, f64) = (2.7, -2.7);
+const MIDDLE: (f64, f64) = (1.0, -0.7);
+/// Points the route drives to in order, for the selector's field preview.
+pub const PATH: &[(f64, f64)] = &[GOAL, RING, CORNER, MIDDLE];
+
 async fn route(robot: &mut Robot) {
-    robot
-        .tracking
-        .borrow_mut()
-        .set_pose((1.0 * TILES_TO_MM, -2.5 * TILES_TO_MM, -FRAC_PI_2).into());
+    robot.tracking.borrow_mut().set_pose(START.into());
 
-    // stake
+    robot.route_step.set("goal");
     let mut clamp = robot.clamp.clone();
     robot
         .drivetrain
         .action(drivetrain_actions::drive_to_point(
-            (1.0, -0.8).into(),
+            GOAL.into(),
             true,
             CONFIG
                 .with_linear_error_tolerance(200.0)
@@ -26,18 +33,19 @@ async fn route(robot: &mut Robot) {
         ))
         .with_callback(move |pose| {
             if pose.y() > -1.1 * TILES_TO_MM {
-                clamp.extend();
+                clamp.grab_without_sensor();
             }
         })
         .await;
+    robot.clamp.grab().await;
     robot.intake.run(vexide::prelude::Direction::Forward);
     sleep(Duration::from_millis(200)).await;
 
-    // Get ring at (2.0, -1.0)
+    robot.route_step.set("ring");
     robot
         .drivetrain
         .action(drivetrain_actions::drive_to_point(
-            (2.1, -1.0).into(),
+            RING.into(),
             false,
             CONFIG
                 .with_linear_error_tolerance(200.0)
@@ -46,21 +54,25 @@ async fn route(robot: &mut Robot) {
         .await;
     sleep(Duration::from_millis(200)).await;
 
-    // Corner
+    robot.route_step.set("corner");
     let intake = robot.intake.clone();
     robot
         .drivetrain
         .action(drivetrain_actions::drive_to_point(
-            (2.7, -2.7).into(),
+            CORNER.into(),
             false,
             CONFIG.with_linear_error_tolerance(100.0),
         ))
         .with_callback(move |pose| {
             if pose.y() < -2.0 * TILES_TO_MM {
+                intake.set_speeds(crate::subsystems::intake::IntakeSpeeds::EJECT);
                 intake.run(vexide::prelude::Direction::Reverse);
             }
         })
         .await;
+    robot
+        .intake
+        .set_speeds(crate::subsystems::intake::IntakeSpeeds::MOBILE_GOAL);
     robot.intake.run(vexide::prelude::Direction::Forward);
     sleep(Duration::from_millis(400)).await;
     robot
@@ -90,7 +102,7 @@ async fn route(robot: &mut Robot) {
                 .with_linear_velocity_tolerance(300.0),
         ))
         .await;
-    // Since we have time, an extra pass
+    robot.route_step.set("extra pass");
     robot
         .drivetrain
         .action(drivetrain_actions::forward(
@@ -108,17 +120,23 @@ async fn route(robot: &mut Robot) {
         ))
         .await;
 
-    // Drive to middle
+    robot.route_step.set("middle");
     robot.intake.stop(); // to prevent flying ring
-    robot
-        .lady_brown
-        .set_state(crate::subsystems::lady_brown::LadyBrownState::MaxExpansion);
+    let lady_brown = robot.lady_brown.clone();
+    let arm = vexide::prelude::spawn(async move {
+        if let Err(err) = lady_brown
+            .move_to(crate::subsystems::lady_brown::LadyBrownState::MaxExpansion)
+            .await
+        {
+            log::warn!("failed to raise the arm to touch the ladder: {}", err);
+        }
+    });
     let intake = robot.intake.clone();
     let mut flag = false;
     robot
         .drivetrain
         .action(drivetrain_actions::drive_to_point(
-            (1.0, -0.7).into(),
+            MIDDLE.into(),
             false,
             CONFIG,
         ))
@@ -136,6 +154,7 @@ async fn route(robot: &mut Robot) {
             CONFIG,
         ))
         .await;
+    arm.await;
 }
 
 pub async fn blue_positive(robot: &mut Robot) {
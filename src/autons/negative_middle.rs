@@ -7,13 +7,29 @@ use crate::{
     Robot,
 };
 
+/// Starting pose (x and y in mm, heading in radians) on the unreversed side.
+pub const START: (f64, f64, f64) = (-280.0, -1360.0, -0.74);
+// Points the route drives to, in tiles
+const GOAL: (f64, f64) = (-1.1, -1.0);
+const CENTER_RINGS: (f64, f64) = (-0.49, -0.41);
+const CENTER_BACK_OUT: (f64, f64) = (-1.5, -1.5);
+const FIRST_RING: (f64, f64) = (-1.3, -1.0);
+const SECOND_RING: (f64, f64) = (-2.0, -1.0);
+const CORNER: (f64, f64) = (-2.9, -2.7);
+/// Points the route drives to in order, for the selector's field preview.
+pub const PATH: &[(f64, f64)] = &[
+    GOAL,
+    CENTER_RINGS,
+    CENTER_BACK_OUT,
+    FIRST_RING,
+    SECOND_RING,
+    CORNER,
+];
+
 async fn route(robot: &mut Robot) {
-    robot
-        .tracking
-        .borrow_mut()
-        .set_pose((-280.0, -1360.0, -0.74).into());
+    robot.tracking.borrow_mut().set_pose(START.into());
 
-    // Alliance score
+    robot.route_step.set("alliance stake");
     robot
         .drivetrain
         .action(drivetrain_actions::forward(
@@ -22,20 +38,23 @@ async fn route(robot: &mut Robot) {
         ))
         .await;
     sleep(Duration::from_micros(100)).await;
-    robot
+    let alliance_stake = robot
         .lady_brown
-        .set_state(crate::subsystems::lady_brown::LadyBrownState::MaxExpansion);
-    sleep(Duration::from_millis(700)).await;
+        .preset("alliance stake")
+        .unwrap_or(crate::subsystems::lady_brown::LadyBrownState::MaxExpansion);
+    if let Err(err) = robot.lady_brown.move_to(alliance_stake).await {
+        log::warn!("failed to score alliance stake: {}", err);
+    }
     robot
         .lady_brown
         .set_state(crate::subsystems::lady_brown::LadyBrownState::Initial);
 
-    // Get goal at (1.0, -1.0)
+    robot.route_step.set("goal");
     let mut clamp = robot.clamp.clone();
     robot
         .drivetrain
         .action(drivetrain_actions::drive_to_point(
-            (-1.1, -1.0).into(),
+            GOAL.into(),
             true,
             CONFIG
                 .with_linear_error_tolerance(100.0)
@@ -43,18 +62,19 @@ async fn route(robot: &mut Robot) {
         ))
         .with_callback(move |pose| {
             if pose.y() > -1.1 * TILES_TO_MM {
-                clamp.extend();
+                clamp.grab_without_sensor();
             }
         })
         .await;
+    robot.clamp.grab().await;
 
-    // Get center rings
+    robot.route_step.set("center rings");
     robot.doinker.non_dominant().extend();
     robot.intake_raiser.extend();
     robot
         .drivetrain
         .action(drivetrain_actions::drive_to_point(
-            (-0.49, -0.41).into(),
+            CENTER_RINGS.into(),
             false,
             CONFIG
                 .with_linear_error_tolerance(20.0)
@@ -65,7 +85,7 @@ async fn route(robot: &mut Robot) {
     robot
         .drivetrain
         .action(drivetrain_actions::drive_to_point(
-            (-1.5, -1.5).into(),
+            CENTER_BACK_OUT.into(),
             true,
             CONFIG
                 .with_turn_error_tolerance(0.3)
@@ -78,7 +98,7 @@ async fn route(robot: &mut Robot) {
     robot
         .drivetrain
         .action(drivetrain_actions::drive_to_point(
-            (-1.3, -1.0).into(),
+            FIRST_RING.into(),
             false,
             CONFIG.with_linear_error_tolerance(150.0),
         ))
@@ -86,7 +106,7 @@ async fn route(robot: &mut Robot) {
     robot
         .drivetrain
         .action(drivetrain_actions::drive_to_point(
-            (-2.0, -1.0).into(),
+            SECOND_RING.into(),
             false,
             CONFIG,
         ))
@@ -95,16 +115,20 @@ async fn route(robot: &mut Robot) {
     robot
         .drivetrain
         .action(drivetrain_actions::drive_to_point(
-            (-2.9, -2.7).into(),
+            CORNER.into(),
             false,
             CONFIG.with_linear_error_tolerance(100.0),
         ))
         .with_callback(move |pose| {
             if pose.y() < -2.1 * TILES_TO_MM {
+                intake.set_speeds(crate::subsystems::intake::IntakeSpeeds::EJECT);
                 intake.run(vexide::prelude::Direction::Reverse);
             }
         })
         .await;
+    robot
+        .intake
+        .set_speeds(crate::subsystems::intake::IntakeSpeeds::MOBILE_GOAL);
     robot.intake.run(vexide::prelude::Direction::Forward);
     sleep(Duration::from_millis(500)).await;
     robot
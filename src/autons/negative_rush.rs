@@ -7,12 +7,20 @@ use crate::{
     Robot,
 };
 
+/// Starting pose (x and y in mm, heading in radians) on the unreversed side.
+pub const START: (f64, f64, f64) = (-600.0 * 2.0 - 350.0, -600.0 * 2.0 - 120.0, FRAC_PI_2);
+// Points the route drives to, in tiles
+const RUSH: (f64, f64) = (-2.7, -0.5);
+const LINE_UP: (f64, f64) = (-1.6, -0.4);
+const GOAL: (f64, f64) = (-0.9, -1.1);
+const RING: (f64, f64) = (-2.0, -2.4);
+const CORNER: (f64, f64) = (-2.7, -2.7);
+/// Points the route drives to in order, for the selector's field preview.
+pub const PATH: &[(f64, f64)] = &[RUSH, LINE_UP, GOAL, RING, CORNER];
+
 async fn route(robot: &mut Robot) {
-    // Starting position
-    robot
-        .tracking
-        .borrow_mut()
-        .set_pose((-600.0 * 2.0 - 350.0, -600.0 * 2.0 - 120.0, FRAC_PI_2).into());
+    robot.route_step.set("rush");
+    robot.tracking.borrow_mut().set_pose(START.into());
 
     //doinker
 
@@ -21,7 +29,7 @@ async fn route(robot: &mut Robot) {
     robot
         .drivetrain
         .action(drivetrain_actions::drive_to_point(
-            (-2.7, -0.5).into(),
+            RUSH.into(),
             false,
             CONFIG
                 .with_linear_error_tolerance(200.0)
@@ -35,7 +43,7 @@ async fn route(robot: &mut Robot) {
     robot
         .drivetrain
         .action(drivetrain_actions::drive_to_point(
-            (-1.6, -0.4).into(),
+            LINE_UP.into(),
             false,
             CONFIG,
         ))
@@ -44,22 +52,22 @@ async fn route(robot: &mut Robot) {
     robot
         .drivetrain
         .action(drivetrain_actions::drive_to_point(
-            (-0.9, -1.1).into(),
+            GOAL.into(),
             true,
             CONFIG,
         ))
         .with_callback(move |pose| {
             if pose.x() > -1.3 * TILES_TO_MM {
-                clamp.extend();
+                clamp.grab_without_sensor();
             }
         })
         .await;
-    robot.clamp.extend();
+    robot.clamp.grab().await;
     robot.intake.run(Direction::Forward);
     robot
         .drivetrain
         .action(drivetrain_actions::drive_to_point(
-            (-2.0, -2.4).into(),
+            RING.into(),
             false,
             CONFIG,
         ))
@@ -69,7 +77,7 @@ async fn route(robot: &mut Robot) {
     robot
         .drivetrain
         .action(drivetrain_actions::drive_to_point(
-            (-2.7, -2.7).into(),
+            CORNER.into(),
             false,
             CONFIG,
         ))
@@ -84,7 +92,7 @@ pub async fn blue(robot: &mut Robot) {
         .set_accept(Some(crate::subsystems::intake::RingColor::Blue));
     robot
         .doinker
-        .set_mirrored_state(libdoxa::subsystems::pneumatic::MirroredState::Mirrored);
+        .set_alliance(crate::subsystems::intake::RingColor::Blue);
 }
 
 pub async fn red(robot: &mut Robot) {
@@ -7,13 +7,20 @@ use crate::{
     Robot,
 };
 
+/// Starting pose (x and y in mm, heading in radians) on the unreversed side.
+pub const START: (f64, f64, f64) = (-280.0, -1360.0, -0.74);
+// Points the route drives to, in tiles
+const GOAL: (f64, f64) = (-1.1, -1.0);
+const RING: (f64, f64) = (-2.0, -1.0);
+const CORNER: (f64, f64) = (-2.8, -2.8);
+const LADDER: (f64, f64) = (-0.2, -0.9);
+/// Points the route drives to in order, for the selector's field preview.
+pub const PATH: &[(f64, f64)] = &[GOAL, RING, CORNER, LADDER];
+
 async fn route(robot: &mut Robot) {
-    robot
-        .tracking
-        .borrow_mut()
-        .set_pose((-280.0, -1360.0, -0.74).into());
+    robot.tracking.borrow_mut().set_pose(START.into());
 
-    // Alliance score
+    robot.route_step.set("alliance stake");
     robot
         .drivetrain
         .action(drivetrain_actions::forward(
@@ -22,20 +29,23 @@ async fn route(robot: &mut Robot) {
         ))
         .await;
     sleep(Duration::from_millis(100)).await;
-    robot
+    let alliance_stake = robot
         .lady_brown
-        .set_state(crate::subsystems::lady_brown::LadyBrownState::MaxExpansion);
-    sleep(Duration::from_millis(700)).await;
+        .preset("alliance stake")
+        .unwrap_or(crate::subsystems::lady_brown::LadyBrownState::MaxExpansion);
+    if let Err(err) = robot.lady_brown.move_to(alliance_stake).await {
+        log::warn!("failed to score alliance stake: {}", err);
+    }
     robot
         .lady_brown
         .set_state(crate::subsystems::lady_brown::LadyBrownState::Initial);
 
-    // Get goal at (1.0, -1.0)
+    robot.route_step.set("goal");
     let mut clamp = robot.clamp.clone();
     robot
         .drivetrain
         .action(drivetrain_actions::drive_to_point(
-            (-1.1, -1.0).into(),
+            GOAL.into(),
             true,
             CONFIG
                 .with_linear_error_tolerance(100.0)
@@ -43,17 +53,18 @@ async fn route(robot: &mut Robot) {
         ))
         .with_callback(move |pose| {
             if pose.y() > -1.1 * TILES_TO_MM {
-                clamp.extend();
+                clamp.grab_without_sensor();
             }
         })
         .await;
+    robot.clamp.grab().await;
 
-    // Ring at (-2.0, -1.0)
+    robot.route_step.set("ring");
     robot.intake.run(vexide::prelude::Direction::Forward);
     robot
         .drivetrain
         .action(drivetrain_actions::drive_to_point(
-            (-2.0, -1.0).into(),
+            RING.into(),
             false,
             CONFIG,
         ))
@@ -62,16 +73,20 @@ async fn route(robot: &mut Robot) {
     robot
         .drivetrain
         .action(drivetrain_actions::drive_to_point(
-            (-2.8, -2.8).into(),
+            CORNER.into(),
             false,
             CONFIG.with_linear_error_tolerance(100.0),
         ))
         .with_callback(move |pose| {
             if pose.y() < -2.0 * TILES_TO_MM {
+                intake.set_speeds(crate::subsystems::intake::IntakeSpeeds::EJECT);
                 intake.run(vexide::prelude::Direction::Reverse);
             }
         })
         .await;
+    robot
+        .intake
+        .set_speeds(crate::subsystems::intake::IntakeSpeeds::MOBILE_GOAL);
     robot.intake.run(vexide::prelude::Direction::Forward);
     sleep(Duration::from_millis(500)).await;
     robot
@@ -92,13 +107,19 @@ async fn route(robot: &mut Robot) {
         ))
         .await;
     sleep(Duration::from_millis(500)).await;
-    robot
-        .lady_brown
-        .set_state(crate::subsystems::lady_brown::LadyBrownState::MaxExpansion);
+    let lady_brown = robot.lady_brown.clone();
+    let arm = vexide::prelude::spawn(async move {
+        if let Err(err) = lady_brown
+            .move_to(crate::subsystems::lady_brown::LadyBrownState::MaxExpansion)
+            .await
+        {
+            log::warn!("failed to raise the arm to touch the ladder: {}", err);
+        }
+    });
     robot
         .drivetrain
         .action(drivetrain_actions::drive_to_point(
-            (-0.2, -0.9).into(),
+            LADDER.into(),
             false,
             CONFIG.with_linear_error_tolerance(100.0),
         ))
@@ -110,6 +131,7 @@ async fn route(robot: &mut Robot) {
             CONFIG,
         ))
         .await;
+    arm.await;
 }
 
 pub async fn blue(robot: &mut Robot) {
@@ -7,13 +7,20 @@ use crate::{
     Robot,
 };
 
+/// Starting pose (x and y in mm, heading in radians) on the unreversed side.
+pub const START: (f64, f64, f64) = (280.0, -1360.0, 0.74 - PI);
+// Points the route drives to, in tiles
+const GOAL: (f64, f64) = (1.15, -0.8);
+const RING: (f64, f64) = (2.1, -1.0);
+const STAKE_RING: (f64, f64) = (-0.2, -1.9);
+const LADDER: (f64, f64) = (0.0, 0.9);
+/// Points the route drives to in order, for the selector's field preview.
+pub const PATH: &[(f64, f64)] = &[GOAL, RING, STAKE_RING, LADDER];
+
 async fn route(robot: &mut Robot) {
-    robot
-        .tracking
-        .borrow_mut()
-        .set_pose((280.0, -1360.0, 0.74 - PI).into());
+    robot.tracking.borrow_mut().set_pose(START.into());
 
-    // Alliance score
+    robot.route_step.set("alliance stake");
     robot
         .drivetrain
         .action(drivetrain_actions::forward(
@@ -22,10 +29,13 @@ async fn route(robot: &mut Robot) {
         ))
         .await;
     sleep(Duration::from_millis(100)).await;
-    robot
+    let alliance_stake = robot
         .lady_brown
-        .set_state(crate::subsystems::lady_brown::LadyBrownState::MaxExpansion);
-    sleep(Duration::from_millis(800)).await;
+        .preset("alliance stake")
+        .unwrap_or(crate::subsystems::lady_brown::LadyBrownState::MaxExpansion);
+    if let Err(err) = robot.lady_brown.move_to(alliance_stake).await {
+        log::warn!("failed to score alliance stake: {}", err);
+    }
     robot
         .lady_brown
         .set_state(crate::subsystems::lady_brown::LadyBrownState::Initial);
@@ -33,7 +43,7 @@ async fn route(robot: &mut Robot) {
     robot
         .drivetrain
         .action(drivetrain_actions::drive_to_point(
-            (1.15, -0.8).into(),
+            GOAL.into(),
             true,
             CONFIG
                 .with_linear_error_tolerance(200.0)
@@ -41,17 +51,18 @@ async fn route(robot: &mut Robot) {
         ))
         .with_callback(move |pose| {
             if pose.y() > -1.1 * TILES_TO_MM {
-                clamp.extend();
+                clamp.grab_without_sensor();
             }
         })
         .await;
+    robot.clamp.grab().await;
     robot.intake.run(vexide::prelude::Direction::Forward);
 
-    // Get ring at (2.0, -1.0)
+    robot.route_step.set("ring");
     robot
         .drivetrain
         .action(drivetrain_actions::drive_to_point(
-            (2.1, -1.0).into(),
+            RING.into(),
             false,
             CONFIG
                 .with_linear_error_tolerance(200.0)
@@ -60,12 +71,12 @@ async fn route(robot: &mut Robot) {
         .await;
     sleep(Duration::from_millis(200)).await;
 
-    // Get the ring in front of alliance stake
+    robot.route_step.set("stake ring");
     let mut intake_raiser = robot.intake_raiser.clone();
     robot
         .drivetrain
         .action(drivetrain_actions::drive_to_point(
-            (-0.2, -1.9).into(),
+            STAKE_RING.into(),
             false,
             CONFIG
                 .with_linear_error_tolerance(200.0)
@@ -80,14 +91,20 @@ async fn route(robot: &mut Robot) {
         })
         .await;
 
-    // Drive to bar touch
-    robot
-        .lady_brown
-        .set_state(crate::subsystems::lady_brown::LadyBrownState::MaxExpansion);
+    robot.route_step.set("ladder");
+    let lady_brown = robot.lady_brown.clone();
+    let arm = vexide::prelude::spawn(async move {
+        if let Err(err) = lady_brown
+            .move_to(crate::subsystems::lady_brown::LadyBrownState::MaxExpansion)
+            .await
+        {
+            log::warn!("failed to raise the arm to touch the ladder: {}", err);
+        }
+    });
     robot
         .drivetrain
         .action(drivetrain_actions::drive_to_point(
-            (0.0, 0.9).into(),
+            LADDER.into(),
             false,
             CONFIG
                 .with_turn_error_tolerance(0.1)
@@ -96,6 +113,7 @@ async fn route(robot: &mut Robot) {
                 .with_linear_error_tolerance(100.0),
         ))
         .await;
+    arm.await;
 }
 
 pub async fn blue(robot: &mut Robot) {
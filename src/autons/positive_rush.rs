@@ -7,13 +7,21 @@ use crate::{
     Robot,
 };
 
+/// Starting pose (x and y in mm, heading in radians) on the unreversed side.
+pub const START: (f64, f64, f64) = (600.0 * 2.0, -600.0 * 2.5, FRAC_PI_2);
+// Points the route drives to, in tiles
+const RUSH: (f64, f64) = (2.0, -0.4);
+const FIRST_GOAL: (f64, f64) = (0.7, -1.2);
+const SECOND_GOAL: (f64, f64) = (2.4, -0.25);
+const RING: (f64, f64) = (2.35, -2.3);
+const CORNER: (f64, f64) = (2.8, -2.8);
+/// Points the route drives to in order, for the selector's field preview.
+pub const PATH: &[(f64, f64)] = &[RUSH, FIRST_GOAL, SECOND_GOAL, RING, CORNER];
+
 async fn route(robot: &mut Robot) {
     sleep(Duration::from_millis(1000)).await;
-    // Starting position
-    robot
-        .tracking
-        .borrow_mut()
-        .set_pose((600.0 * 2.0, -600.0 * 2.5, FRAC_PI_2).into());
+    robot.route_step.set("rush");
+    robot.tracking.borrow_mut().set_pose(START.into());
 
     robot.intake.run(vexide::prelude::Direction::Forward);
     robot.doinker.non_dominant().extend();
@@ -28,7 +36,7 @@ async fn route(robot: &mut Robot) {
     robot
         .drivetrain
         .action(drivetrain_actions::drive_to_point(
-            (2.0, -0.4).into(),
+            RUSH.into(),
             false,
             CONFIG
                 .with_turn_tolerance_duration(core::time::Duration::ZERO)
@@ -71,7 +79,7 @@ async fn route(robot: &mut Robot) {
     robot
         .drivetrain
         .action(drivetrain_actions::drive_to_point(
-            (0.7, -1.2).into(),
+            FIRST_GOAL.into(),
             true,
             CONFIG
                 .with_linear_error_tolerance(100.0)
@@ -80,10 +88,11 @@ async fn route(robot: &mut Robot) {
         ))
         .with_callback(move |pose| {
             if pose.x() < 1.1 * crate::subsystems::drivetrain_actions::TILES_TO_MM {
-                clamp.extend();
+                clamp.grab_without_sensor();
             }
         })
         .await;
+    robot.clamp.grab().await;
     let old_accept = robot.intake.accept();
     robot.intake.set_accept(None);
     robot.intake.run(vexide::prelude::Direction::Forward);
@@ -98,7 +107,7 @@ async fn route(robot: &mut Robot) {
     robot
         .drivetrain
         .action(drivetrain_actions::drive_to_point(
-            (2.4, -0.25).into(),
+            SECOND_GOAL.into(),
             true,
             CONFIG
                 .with_linear_error_tolerance(100.0)
@@ -110,9 +119,9 @@ async fn route(robot: &mut Robot) {
             }
         })
         .await;
-    robot.clamp.extend();
+    robot.clamp.grab().await;
 
-    // Preload
+    robot.route_step.set("preload");
     robot.intake.run(vexide::prelude::Direction::Forward);
     robot
         .drivetrain
@@ -127,7 +136,7 @@ async fn route(robot: &mut Robot) {
     robot
         .drivetrain
         .action(drivetrain_actions::drive_to_point(
-            (2.35, -2.3).into(),
+            RING.into(),
             false,
             CONFIG
                 .with_turn_error_tolerance(0.2)
@@ -136,13 +145,13 @@ async fn route(robot: &mut Robot) {
         ))
         .await;
 
-    // Corner ring
+    robot.route_step.set("corner");
     sleep(Duration::from_millis(1000)).await;
     let intake = robot.intake.clone();
     robot
         .drivetrain
         .action(drivetrain_actions::drive_to_point(
-            (2.8, -2.8).into(),
+            CORNER.into(),
             false,
             CONFIG
                 .with_linear_error_tolerance(200.0)
@@ -151,10 +160,14 @@ async fn route(robot: &mut Robot) {
         ))
         .with_callback(move |pose| {
             if pose.x() > 2.6 {
+                intake.set_speeds(crate::subsystems::intake::IntakeSpeeds::EJECT);
                 intake.run(vexide::prelude::Direction::Reverse);
             }
         })
         .await;
+    robot
+        .intake
+        .set_speeds(crate::subsystems::intake::IntakeSpeeds::MOBILE_GOAL);
     robot.intake.run(vexide::prelude::Direction::Forward);
     sleep(Duration::from_millis(500)).await;
     robot
@@ -181,7 +194,7 @@ pub async fn blue(robot: &mut Robot) {
         .set_accept(Some(crate::subsystems::intake::RingColor::Blue));
     robot
         .doinker
-        .set_mirrored_state(libdoxa::subsystems::pneumatic::MirroredState::Mirrored);
+        .set_alliance(crate::subsystems::intake::RingColor::Blue);
     route(robot).await;
 }
 
@@ -192,6 +205,6 @@ pub async fn red(robot: &mut Robot) {
         .set_accept(Some(crate::subsystems::intake::RingColor::Red));
     robot
         .doinker
-        .set_mirrored_state(libdoxa::subsystems::pneumatic::MirroredState::Normal);
+        .set_alliance(crate::subsystems::intake::RingColor::Red);
     route(robot).await;
 }
@@ -0,0 +1,426 @@
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use log::info;
+use snafu::Snafu;
+use vexide::{
+    devices::{adi::AdiPort, smart::SmartPort},
+    prelude::{Direction, Gearset},
+};
+
+/// A motor or rotation sensor and the direction it is mounted in. Written as
+/// the port number, negated if the device is reversed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PortConfig {
+    pub port: u8,
+    pub direction: Direction,
+}
+
+impl PortConfig {
+    const fn forward(port: u8) -> Self {
+        Self {
+            port,
+            direction: Direction::Forward,
+        }
+    }
+
+    const fn reverse(port: u8) -> Self {
+        Self {
+            port,
+            direction: Direction::Reverse,
+        }
+    }
+}
+
+#[derive(Debug, Snafu)]
+pub enum ConfigError {
+    #[snafu(display("line {}: expected `key = value`", line))]
+    InvalidLine { line: usize },
+    #[snafu(display("line {}: unknown key `{}`", line, key))]
+    UnknownKey { line: usize, key: String },
+    #[snafu(display("line {}: invalid value `{}` for `{}`", line, value, key))]
+    InvalidValue {
+        line: usize,
+        key: String,
+        value: String,
+    },
+    #[snafu(display("{}: smart port {} does not exist", device, port))]
+    SmartPortOutOfRange { device: &'static str, port: u8 },
+    #[snafu(display("{}: ADI port {} does not exist", device, adi_name(*port)))]
+    AdiPortOutOfRange { device: &'static str, port: u8 },
+    #[snafu(display("{} and {} are both on smart port {}", first, second, port))]
+    DuplicateSmartPort {
+        first: &'static str,
+        second: &'static str,
+        port: u8,
+    },
+    #[snafu(display(
+        "{} and {} are both on ADI port {}",
+        first,
+        second,
+        adi_name(*port)
+    ))]
+    DuplicateAdiPort {
+        first: &'static str,
+        second: &'static str,
+        port: u8,
+    },
+    #[snafu(display("{} has no motors", device))]
+    NoMotors { device: &'static str },
+    #[snafu(display("{} must be a positive number, got {}", key, value))]
+    NotPositive { key: &'static str, value: f64 },
+    #[snafu(display("{} must be a number, got {}", key, value))]
+    NotFinite { key: &'static str, value: f64 },
+}
+
+/// Returns the letter printed on the brain for ADI port `port` (1-8).
+fn adi_name(port: u8) -> char {
+    (b'a' + port.saturating_sub(1)) as char
+}
+
+/// Hardware layout of the robot: which device is on which port, and the
+/// geometry needed by the drivetrain and tracking.
+///
+/// Loaded from [`RobotConfig::CONFIG_PATH`] on the SD card so a broken port
+/// can be swapped at a tournament without rebuilding. The file has one
+/// `key = value` pair per line and only needs to list the values that differ
+/// from [`RobotConfig::defaults`]. Blank lines and lines starting with `#` are
+/// ignored. For example:
+///
+/// ```text
+/// # port 6 is broken
+/// left_drive = -5, -7, -17
+/// clamp = h
+/// ```
+///
+/// Smart ports are numbers, negated for reversed motors and sensors. ADI ports
+/// are letters, in either case.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RobotConfig {
+    pub left_drive: Vec<PortConfig>,
+    pub right_drive: Vec<PortConfig>,
+    pub drive_gearset: Gearset,
+    /// Drive wheel circumference in mm.
+    pub drive_circumference: f64,
+    pub inertial: u8,
+    pub tracking_wheel: PortConfig,
+    /// Tracking wheel circumference in mm.
+    pub tracking_wheel_circumference: f64,
+    /// Distance of the tracking wheel from the tracking center in mm.
+    pub tracking_wheel_offset: f64,
+
+    pub intake: PortConfig,
+    pub intake_gearset: Gearset,
+    pub vision: u8,
+    pub optical: u8,
+    pub line_tracker: u8,
+
+    pub lady_brown: Vec<PortConfig>,
+    pub lady_brown_gear_ratio: f64,
+    pub lady_brown_limit: u8,
+
+    pub clamp: u8,
+    /// Distance sensor that detects a mobile goal in the clamp, if there is one.
+    pub goal_sensor: Option<u8>,
+    /// Distance sensors facing left and backwards, used to check that the
+    /// robot is placed on the route's starting pose.
+    pub placement_left: Option<u8>,
+    pub placement_back: Option<u8>,
+    pub doinker_left: u8,
+    pub doinker_right: u8,
+    pub intake_raiser: u8,
+}
+
+impl RobotConfig {
+    pub const CONFIG_PATH: &str = "robot.cfg";
+
+    pub fn defaults() -> Self {
+        Self {
+            left_drive: vec![
+                PortConfig::reverse(5),
+                PortConfig::reverse(6),
+                PortConfig::reverse(17),
+            ],
+            right_drive: vec![
+                PortConfig::forward(3),
+                PortConfig::forward(9),
+                PortConfig::forward(10),
+            ],
+            drive_gearset: Gearset::Blue,
+            drive_circumference: 164.0,
+            inertial: 18,
+            tracking_wheel: PortConfig::forward(19),
+            tracking_wheel_circumference: 158.0,
+            tracking_wheel_offset: 43.0,
+
+            intake: PortConfig::reverse(4),
+            intake_gearset: Gearset::Blue,
+            vision: 11,
+            optical: 15,
+            line_tracker: 7,
+
+            lady_brown: vec![PortConfig::forward(1), PortConfig::reverse(2)],
+            lady_brown_gear_ratio: 3.0,
+            lady_brown_limit: 6,
+
+            clamp: 2,
+            goal_sensor: None,
+            placement_left: None,
+            placement_back: None,
+            doinker_left: 4,
+            doinker_right: 5,
+            intake_raiser: 3,
+        }
+    }
+
+    pub fn parse(source: &str) -> Result<Self, ConfigError> {
+        let mut config = Self::defaults();
+        for (i, line) in source.lines().enumerate() {
+            let line_number = i + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or(ConfigError::InvalidLine { line: line_number })?;
+            let (key, value) = (key.trim(), value.trim());
+            match config.set(key, value) {
+                Some(true) => {}
+                Some(false) => {
+                    return Err(ConfigError::UnknownKey {
+                        line: line_number,
+                        key: key.to_string(),
+                    })
+                }
+                None => {
+                    return Err(ConfigError::InvalidValue {
+                        line: line_number,
+                        key: key.to_string(),
+                        value: value.to_string(),
+                    })
+                }
+            }
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Sets `key` to `value`. Returns `None` if the value is invalid and
+    /// `Some(false)` if the key is unknown.
+    fn set(&mut self, key: &str, value: &str) -> Option<bool> {
+        match key {
+            "left_drive" => self.left_drive = parse_port_list(value)?,
+            "right_drive" => self.right_drive = parse_port_list(value)?,
+            "drive_gearset" => self.drive_gearset = parse_gearset(value)?,
+            "drive_circumference" => self.drive_circumference = value.parse().ok()?,
+            "inertial" => self.inertial = value.parse().ok()?,
+            "tracking_wheel" => self.tracking_wheel = parse_port(value)?,
+            "tracking_wheel_circumference" => {
+                self.tracking_wheel_circumference = value.parse().ok()?
+            }
+            "tracking_wheel_offset" => self.tracking_wheel_offset = value.parse().ok()?,
+            "intake" => self.intake = parse_port(value)?,
+            "intake_gearset" => self.intake_gearset = parse_gearset(value)?,
+            "vision" => self.vision = value.parse().ok()?,
+            "optical" => self.optical = value.parse().ok()?,
+            "line_tracker" => self.line_tracker = parse_adi(value)?,
+            "lady_brown" => self.lady_brown = parse_port_list(value)?,
+            "lady_brown_gear_ratio" => self.lady_brown_gear_ratio = value.parse().ok()?,
+            "lady_brown_limit" => self.lady_brown_limit = parse_adi(value)?,
+            "clamp" => self.clamp = parse_adi(value)?,
+            "goal_sensor" => self.goal_sensor = parse_optional_port(value)?,
+            "placement_left" => self.placement_left = parse_optional_port(value)?,
+            "placement_back" => self.placement_back = parse_optional_port(value)?,
+            "doinker_left" => self.doinker_left = parse_adi(value)?,
+            "doinker_right" => self.doinker_right = parse_adi(value)?,
+            "intake_raiser" => self.intake_raiser = parse_adi(value)?,
+            _ => return Some(false),
+        }
+        Some(true)
+    }
+
+    /// Checks that every port exists, no port is used by two devices and the
+    /// geometry is usable.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        for (key, value) in [
+            ("drive_circumference", self.drive_circumference),
+            (
+                "tracking_wheel_circumference",
+                self.tracking_wheel_circumference,
+            ),
+            ("lady_brown_gear_ratio", self.lady_brown_gear_ratio),
+        ] {
+            // Also rejects NaN
+            if !(value > 0.0 && value.is_finite()) {
+                return Err(ConfigError::NotPositive { key, value });
+            }
+        }
+        if !self.tracking_wheel_offset.is_finite() {
+            return Err(ConfigError::NotFinite {
+                key: "tracking_wheel_offset",
+                value: self.tracking_wheel_offset,
+            });
+        }
+
+        for (device, motors) in [
+            ("left drive", &self.left_drive),
+            ("right drive", &self.right_drive),
+            ("lady brown", &self.lady_brown),
+        ] {
+            if motors.is_empty() {
+                return Err(ConfigError::NoMotors { device });
+            }
+        }
+
+        let mut smart: Vec<(&'static str, u8)> = Vec::new();
+        smart.extend(self.left_drive.iter().map(|m| ("left drive", m.port)));
+        smart.extend(self.right_drive.iter().map(|m| ("right drive", m.port)));
+        smart.extend(self.lady_brown.iter().map(|m| ("lady brown", m.port)));
+        smart.extend([
+            ("inertial", self.inertial),
+            ("tracking wheel", self.tracking_wheel.port),
+            ("intake", self.intake.port),
+            ("vision", self.vision),
+            ("optical", self.optical),
+        ]);
+        smart.extend(self.goal_sensor.map(|port| ("goal sensor", port)));
+        smart.extend(self.placement_left.map(|port| ("placement left", port)));
+        smart.extend(self.placement_back.map(|port| ("placement back", port)));
+        for (i, &(device, port)) in smart.iter().enumerate() {
+            if !(1..=21).contains(&port) {
+                return Err(ConfigError::SmartPortOutOfRange { device, port });
+            }
+            if let Some(&(first, _)) = smart[..i].iter().find(|(_, other)| *other == port) {
+                return Err(ConfigError::DuplicateSmartPort {
+                    first,
+                    second: device,
+                    port,
+                });
+            }
+        }
+
+        let adi = [
+            ("line tracker", self.line_tracker),
+            ("lady brown limit", self.lady_brown_limit),
+            ("clamp", self.clamp),
+            ("left doinker", self.doinker_left),
+            ("right doinker", self.doinker_right),
+            ("intake raiser", self.intake_raiser),
+        ];
+        for (i, &(device, port)) in adi.iter().enumerate() {
+            if !(1..=8).contains(&port) {
+                return Err(ConfigError::AdiPortOutOfRange { device, port });
+            }
+            if let Some(&(first, _)) = adi[..i].iter().find(|(_, other)| *other == port) {
+                return Err(ConfigError::DuplicateAdiPort {
+                    first,
+                    second: device,
+                    port,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Loads the config from the SD card, or [`Self::defaults`] if the file
+    /// is missing.
+    ///
+    /// An invalid file is returned as an error so it can be reported, see
+    /// [`crate::diagnostics::SelfTest::config`].
+    pub fn load() -> Result<Self, ConfigError> {
+        let Ok(source) = vexide::fs::read_to_string(Self::CONFIG_PATH) else {
+            return Ok(Self::defaults());
+        };
+        let config = Self::parse(&source)?;
+        info!("loaded robot config from {}", Self::CONFIG_PATH);
+        Ok(config)
+    }
+}
+
+fn parse_port(value: &str) -> Option<PortConfig> {
+    let port: i8 = value.parse().ok()?;
+    Some(PortConfig {
+        port: port.unsigned_abs(),
+        direction: if port < 0 {
+            Direction::Reverse
+        } else {
+            Direction::Forward
+        },
+    })
+}
+
+/// Parses a port number, or `none` for devices the robot doesn't have.
+fn parse_optional_port(value: &str) -> Option<Option<u8>> {
+    match value {
+        "none" => Some(None),
+        _ => Some(Some(value.parse().ok()?)),
+    }
+}
+
+fn parse_port_list(value: &str) -> Option<Vec<PortConfig>> {
+    value
+        .split(',')
+        .map(|port| parse_port(port.trim()))
+        .collect()
+}
+
+fn parse_adi(value: &str) -> Option<u8> {
+    match value.as_bytes() {
+        // The brain labels them A to H
+        &[letter] if letter.is_ascii_alphabetic() => Some(letter.to_ascii_lowercase() - b'a' + 1),
+        _ => None,
+    }
+}
+
+fn parse_gearset(value: &str) -> Option<Gearset> {
+    match value {
+        "red" => Some(Gearset::Red),
+        "green" => Some(Gearset::Green),
+        "blue" => Some(Gearset::Blue),
+        _ => None,
+    }
+}
+
+/// The brain's ports, handed out by number so devices can be placed according
+/// to a [`RobotConfig`].
+pub struct Ports {
+    smart: [Option<SmartPort>; 21],
+    adi: [Option<AdiPort>; 8],
+}
+
+impl Ports {
+    pub fn new(smart: [SmartPort; 21], adi: [AdiPort; 8]) -> Self {
+        Self {
+            smart: smart.map(Some),
+            adi: adi.map(Some),
+        }
+    }
+
+    /// Takes smart port `port`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the port doesn't exist or was already taken, which
+    /// [`RobotConfig::validate`] rules out.
+    pub fn smart(&mut self, port: u8) -> SmartPort {
+        self.smart[port as usize - 1]
+            .take()
+            .expect("smart port used twice")
+    }
+
+    /// Takes ADI port `port`, numbered from 1 for port A.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the port doesn't exist or was already taken, which
+    /// [`RobotConfig::validate`] rules out.
+    pub fn adi(&mut self, port: u8) -> AdiPort {
+        self.adi[port as usize - 1]
+            .take()
+            .expect("ADI port used twice")
+    }
+}
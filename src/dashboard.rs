@@ -0,0 +1,363 @@
+use alloc::{format, rc::Rc, string::String, vec::Vec};
+use core::{cell::RefCell, time::Duration};
+
+use vexide::{
+    competition::{self, CompetitionMode},
+    devices::{
+        battery,
+        display::{Font, FontFamily, FontSize, RenderMode, Text},
+        math::Point2,
+        rgb::Rgb,
+    },
+    prelude::{sleep, spawn, Controller, Display},
+    task::Task,
+};
+
+use crate::{
+    diagnostics, graphics,
+    placement::{PlacementStatus, StartPlacement},
+    selector::TouchSelector,
+    subsystems::{inertial::InertialState, intake::IntakeState, pneumatic::PneumaticState},
+    Robot,
+};
+
+/// How often the dashboard is redrawn. Drawing is slow compared to the
+/// control loops, so keep this modest.
+const REFRESH_PERIOD: Duration = Duration::from_millis(200);
+/// How often touches are checked while the selector is shown.
+const TOUCH_PERIOD: Duration = Duration::from_millis(50);
+
+/// The field map is a square on the left of the screen.
+const FIELD_ORIGIN: Point2<i16> = Point2 { x: 0, y: 0 };
+const FIELD_SIZE: i16 = 240;
+const TEXT_X: i16 = FIELD_SIZE + 6;
+const LINE_HEIGHT: i16 = 19;
+
+/// Name of the route step currently running, shown on the dashboard.
+#[derive(Debug, Clone)]
+pub struct RouteStep(Rc<RefCell<&'static str>>);
+
+impl RouteStep {
+    pub fn new() -> Self {
+        Self(Rc::new(RefCell::new("")))
+    }
+
+    pub fn set(&self, step: &'static str) {
+        log::debug!("route step: {}", step);
+        *self.0.borrow_mut() = step;
+    }
+
+    pub fn get(&self) -> &'static str {
+        *self.0.borrow()
+    }
+}
+
+fn pneumatic_label(state: PneumaticState) -> &'static str {
+    match state {
+        PneumaticState::Extending => "ext..",
+        PneumaticState::Extended => "ext",
+        PneumaticState::Retracting => "ret..",
+        PneumaticState::Retracted => "ret",
+    }
+}
+
+/// Live robot state shown on the brain screen during driver control and
+/// autonomous.
+///
+/// While the robot is disabled the touchscreen route selector is shown
+/// instead, along with whether the robot is placed on the starting pose of
+/// the route that runs next. Startup problems, if there were any, are shown
+/// before it until the screen is tapped so they aren't missed while the robot
+/// is set up.
+/// The battery keeps being checked while disabled, since it drains while the
+/// robot waits for a match.
+pub struct Dashboard {
+    display: Display,
+    robot: DashboardSources,
+    startup_issues: Vec<String>,
+    selector: TouchSelector,
+    placement: StartPlacement,
+    placement_status: PlacementStatus,
+    /// Inertial calibration state shown by the selector.
+    inertial_state: InertialState,
+    /// Placement status last written to the controller, which can drop
+    /// writes.
+    controller_placement: Option<PlacementStatus>,
+    /// Press count of the last handled touch.
+    press_count: i32,
+    /// Whether the battery was already reported as low.
+    low_battery: bool,
+    /// Whether the disabled screen is already drawn, so it's only redrawn
+    /// when touched.
+    showing_disabled: bool,
+}
+
+/// Handles to everything the dashboard shows, cloned from the [`Robot`].
+struct DashboardSources {
+    controller: Rc<RefCell<Controller>>,
+    /// Whether the controller selector's menu is shown.
+    is_selecting: Rc<RefCell<bool>>,
+    inertial: crate::subsystems::inertial::InertialCalibration,
+    tracking: Rc<RefCell<libdoxa::subsystems::tracking::TrackingSubsystem>>,
+    step: RouteStep,
+    intake: crate::subsystems::intake::Intake,
+    lady_brown: crate::subsystems::lady_brown::LadyBrown,
+    clamp: crate::subsystems::Clamp,
+    doinker: crate::subsystems::Doinker,
+    intake_raiser: crate::subsystems::IntakeRaiser,
+    air: crate::subsystems::air::AirBudget,
+    motor_monitor: crate::subsystems::motor_monitor::MotorMonitor,
+}
+
+impl Dashboard {
+    pub fn new(
+        mut display: Display,
+        robot: &Robot,
+        startup_issues: Vec<String>,
+        selector: TouchSelector,
+        placement: StartPlacement,
+    ) -> Self {
+        display.set_render_mode(RenderMode::DoubleBuffered);
+        let press_count = display.touch_status().press_count;
+        Self {
+            display,
+            robot: DashboardSources {
+                controller: robot.controller.clone(),
+                is_selecting: robot.is_selecting.clone(),
+                inertial: robot.inertial.clone(),
+                tracking: robot.tracking.clone(),
+                step: robot.route_step.clone(),
+                intake: robot.intake.clone(),
+                lady_brown: robot.lady_brown.clone(),
+                clamp: robot.clamp.clone(),
+                doinker: robot.doinker.clone(),
+                intake_raiser: robot.intake_raiser.clone(),
+                air: robot.air.clone(),
+                motor_monitor: robot.motor_monitor.clone(),
+            },
+            startup_issues,
+            selector,
+            placement,
+            placement_status: PlacementStatus::Unchecked,
+            inertial_state: robot.inertial.state(),
+            controller_placement: None,
+            press_count,
+            // The self test already reported it at startup
+            low_battery: crate::utils::battery::low_capacity().is_some(),
+            showing_disabled: false,
+        }
+    }
+
+    /// Redraws the dashboard in the background until the task is dropped.
+    pub fn spawn(mut self) -> Task<()> {
+        spawn(async move {
+            loop {
+                self.render();
+                sleep(if self.showing_disabled {
+                    TOUCH_PERIOD
+                } else {
+                    REFRESH_PERIOD
+                })
+                .await;
+            }
+        })
+    }
+
+    /// Returns where the screen was pressed since the last call, if it was.
+    fn take_press(&mut self) -> Option<Point2<i16>> {
+        let touch = self.display.touch_status();
+        if touch.press_count == self.press_count {
+            return None;
+        }
+        self.press_count = touch.press_count;
+        Some(touch.point)
+    }
+
+    /// Checks the placement against the route that runs next and shows it on
+    /// the controller. Returns whether it changed.
+    fn update_placement(&mut self) -> bool {
+        let status = self
+            .selector
+            .next_route()
+            .and_then(|route| route.preview)
+            .map_or(PlacementStatus::Unchecked, |preview| {
+                self.placement.check(&preview)
+            });
+        // The controller selector draws its menu over the same lines
+        if *self.robot.is_selecting.borrow() {
+            self.controller_placement = None;
+        } else if self.controller_placement != Some(status)
+            && let Ok(mut controller) = self.robot.controller.try_borrow_mut()
+            && controller
+                .screen
+                .try_set_text(format!("{}         ", status), 3, 1)
+                .is_ok()
+        {
+            self.controller_placement = Some(status);
+        }
+        if status == self.placement_status {
+            return false;
+        }
+        log::info!("{}", status);
+        self.placement_status = status;
+        true
+    }
+
+    /// Reports a battery that got too low to start a match with, once.
+    /// Returns whether it was reported.
+    fn check_battery(&mut self) -> bool {
+        if self.low_battery {
+            return false;
+        }
+        let Some(capacity) = crate::utils::battery::low_capacity() else {
+            return false;
+        };
+        self.low_battery = true;
+        log::warn!("battery is low ({:.0}%)", capacity);
+        self.startup_issues
+            .push(diagnostics::low_battery_line(capacity));
+        if let Ok(mut controller) = self.robot.controller.try_borrow_mut() {
+            _ = controller.try_rumble("---");
+            _ = controller
+                .screen
+                .try_set_text(format!("Battery {:.0}%     ", capacity), 1, 1);
+        }
+        true
+    }
+
+    fn render(&mut self) {
+        let press = self.take_press();
+        if competition::mode() == CompetitionMode::Disabled {
+            let mut redraw = !self.showing_disabled;
+            self.showing_disabled = true;
+            redraw |= self.check_battery();
+            if !self.startup_issues.is_empty() {
+                if press.is_none() {
+                    if redraw {
+                        let mut lines = self.startup_issues.clone();
+                        lines.push(String::from("Tap to select a route"));
+                        graphics::render_text(&mut self.display, &lines);
+                        self.display.render();
+                    }
+                    return;
+                }
+                self.startup_issues.clear();
+                redraw = true;
+            } else if let Some(point) = press {
+                redraw |= self.selector.press(point);
+            }
+            redraw |= self.update_placement();
+            let inertial_state = self.robot.inertial.state();
+            redraw |= inertial_state != self.inertial_state;
+            self.inertial_state = inertial_state;
+            if !redraw {
+                return;
+            }
+            self.selector
+                .render(&mut self.display, self.placement_status);
+        } else {
+            self.showing_disabled = false;
+            self.display.erase((0, 0, 0));
+            self.render_field();
+            let lines = self.lines();
+            for (i, line) in lines.iter().enumerate() {
+                self.display.draw_text(
+                    &Text::new(
+                        line,
+                        Font::new(FontSize::SMALL, FontFamily::Monospace),
+                        Point2 {
+                            x: TEXT_X,
+                            y: 2 + i as i16 * LINE_HEIGHT,
+                        },
+                    ),
+                    (255, 255, 255),
+                    None::<Rgb<u8>>,
+                );
+            }
+        }
+        self.display.render();
+    }
+
+    fn render_field(&mut self) {
+        let pose = self.robot.tracking.borrow().pose();
+        graphics::render_field(&mut self.display, FIELD_ORIGIN, FIELD_SIZE);
+        graphics::render_robot(
+            &mut self.display,
+            FIELD_ORIGIN,
+            FIELD_SIZE,
+            (pose.x(), pose.y(), pose.heading),
+            (230, 200, 90),
+        );
+    }
+
+    fn lines(&self) -> Vec<String> {
+        let robot = &self.robot;
+        let pose = robot.tracking.borrow().pose();
+        let intake = match robot.intake.state() {
+            IntakeState::Forward { current_ring, .. } => match current_ring {
+                Some(ring) => format!("fwd ({:?})", ring),
+                None => String::from("fwd"),
+            },
+            IntakeState::PartialIntake { .. } => String::from("partial"),
+            IntakeState::Reverse => String::from("rev"),
+            IntakeState::Stop | IntakeState::StopHold => String::from("stop"),
+        };
+        let arm_state = robot.lady_brown.state();
+        let arm = robot
+            .lady_brown
+            .presets()
+            .name(arm_state)
+            .map(String::from)
+            .unwrap_or_else(|| format!("{:?}", arm_state));
+        let hottest = robot
+            .motor_monitor
+            .motors()
+            .into_iter()
+            .filter_map(|motor| Some((motor.name, motor.sample?.temperature)))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+
+        let mut lines = Vec::new();
+        lines.push(String::from(robot.step.get()));
+        lines.push(format!(
+            "Pose: {:.0}, {:.0} @ {:.0}",
+            pose.x(),
+            pose.y(),
+            pose.heading.to_degrees()
+        ));
+        lines.push(format!(
+            "Intake: {}{}",
+            intake,
+            if robot.intake.capabilities().color_sorting {
+                ""
+            } else {
+                " no sort"
+            }
+        ));
+        lines.push(format!("Arm: {:.0} {}", robot.lady_brown.angle(), arm));
+        if robot.lady_brown.is_overloaded() {
+            lines.push(String::from("  OVERLOADED"));
+        } else if let Some(fault) = robot.lady_brown.fault() {
+            lines.push(format!("  {}", fault));
+        }
+        lines.push(format!("Clamp: {}", pneumatic_label(robot.clamp.state())));
+        lines.push(format!(
+            "Doink: L {} R {}",
+            pneumatic_label(robot.doinker.left.state()),
+            pneumatic_label(robot.doinker.right.state())
+        ));
+        lines.push(format!(
+            "Raiser: {}",
+            pneumatic_label(robot.intake_raiser.state())
+        ));
+        lines.push(format!("Air: {:.0} psi", robot.air.pressure()));
+        if let Some((name, temperature)) = hottest {
+            lines.push(format!("Temp: {} {:.0}C", name, temperature));
+        }
+        lines.push(format!(
+            "Batt: {:.0}% {:.1}V",
+            battery::capacity(),
+            battery::voltage()
+        ));
+        lines
+    }
+}
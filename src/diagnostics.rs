@@ -0,0 +1,182 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt;
+
+use log::{error, info, warn};
+use vexide::{devices::smart::SmartDevice, os::system_version, prelude::Motor};
+
+use crate::{
+    config::{ConfigError, RobotConfig},
+    utils::battery,
+};
+
+/// Motors hotter than this (in °C) are reported since they are about to be
+/// derated by the firmware.
+const MAX_MOTOR_TEMPERATURE: f64 = 50.0;
+/// Oldest VEXos version the program has been tested on, as
+/// `(major, minor, build)`.
+const MIN_VEXOS_VERSION: (u8, u8, u8) = (1, 1, 4);
+
+/// Returns the line shown when the battery is at `capacity` percent, too low
+/// to start a match with.
+pub fn low_battery_line(capacity: f64) -> String {
+    format!("battery at {:.0}%, swap it before the match", capacity)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeviceFault {
+    /// Nothing, or the wrong kind of device, is plugged into the port.
+    Disconnected,
+    /// The motor is hot enough to lose power.
+    Hot { temperature: f64 },
+}
+
+impl fmt::Display for DeviceFault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceFault::Disconnected => f.write_str("missing or wrong device"),
+            DeviceFault::Hot { temperature } => write!(f, "hot ({:.0} C)", temperature),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceReport {
+    pub name: &'static str,
+    pub port: u8,
+    pub fault: Option<DeviceFault>,
+}
+
+/// Results of the boot-time device check.
+///
+/// Devices are checked before they are handed to their subsystems, so a
+/// subsystem can be built without a faulty sensor instead of panicking. Faulty
+/// motors are only reported and stay in their groups, so one that is plugged
+/// back in works again. ADI devices can't report whether they are plugged in
+/// and aren't checked.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelfTest {
+    devices: Vec<DeviceReport>,
+    outdated_firmware: Option<String>,
+    /// Why the robot config couldn't be used, if it couldn't.
+    invalid_config: Option<String>,
+    /// Battery charge in percent, if it is too low to start a match with.
+    low_battery: Option<f64>,
+}
+
+impl SelfTest {
+    pub fn new() -> Self {
+        let version = system_version();
+        let outdated_firmware = ((version.major, version.minor, version.build) < MIN_VEXOS_VERSION)
+            .then(|| format!("{}.{}.{}", version.major, version.minor, version.build));
+        Self {
+            devices: Vec::new(),
+            outdated_firmware,
+            invalid_config: None,
+            low_battery: battery::low_capacity(),
+        }
+    }
+
+    /// Records that the robot config is invalid and the defaults are used.
+    pub fn config(&mut self, err: &ConfigError) {
+        self.invalid_config = Some(err.to_string());
+    }
+
+    fn record(&mut self, name: &'static str, port: u8, fault: Option<DeviceFault>) -> bool {
+        self.devices.push(DeviceReport { name, port, fault });
+        fault.is_none()
+    }
+
+    /// Checks that `device` is plugged in. Returns `true` if it is.
+    pub fn device(&mut self, name: &'static str, device: &impl SmartDevice) -> bool {
+        let fault = (!device.is_connected()).then_some(DeviceFault::Disconnected);
+        self.record(name, device.port_number(), fault)
+    }
+
+    /// Checks that `motor` is plugged in and not overheating.
+    pub fn motor(&mut self, name: &'static str, motor: &Motor) {
+        let fault = if !motor.is_connected() {
+            Some(DeviceFault::Disconnected)
+        } else {
+            match motor.temperature() {
+                Ok(temperature) if temperature > MAX_MOTOR_TEMPERATURE => {
+                    Some(DeviceFault::Hot { temperature })
+                }
+                _ => None,
+            }
+        };
+        self.record(name, motor.port_number(), fault);
+    }
+
+    pub fn faults(&self) -> impl Iterator<Item = &DeviceReport> {
+        self.devices.iter().filter(|device| device.fault.is_some())
+    }
+
+    pub fn passed(&self) -> bool {
+        self.faults().next().is_none()
+            && self.outdated_firmware.is_none()
+            && self.invalid_config.is_none()
+            && self.low_battery.is_none()
+    }
+
+    /// Returns a short description of the problems, for the controller.
+    pub fn summary(&self) -> String {
+        if self.invalid_config.is_some() {
+            format!("Bad {}", RobotConfig::CONFIG_PATH)
+        } else {
+            format!("{} startup issues", self.lines().len())
+        }
+    }
+
+    /// Returns one line per problem, for the brain screen.
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if let Some(err) = &self.invalid_config {
+            lines.push(format!(
+                "invalid {}, using defaults: {}",
+                RobotConfig::CONFIG_PATH,
+                err
+            ));
+        }
+        if let Some(version) = &self.outdated_firmware {
+            lines.push(format!("VEXos {} is outdated, please update", version));
+        }
+        if let Some(capacity) = self.low_battery {
+            lines.push(low_battery_line(capacity));
+        }
+        lines.extend(
+            self.faults()
+                .filter_map(|device| Some((device, device.fault?)))
+                .map(|(device, fault)| format!("port {}: {}: {}", device.port, device.name, fault)),
+        );
+        lines
+    }
+
+    pub fn log(&self) {
+        if self.passed() {
+            info!("self test passed ({} devices)", self.devices.len());
+            return;
+        }
+        if let Some(version) = &self.outdated_firmware {
+            warn!("VEXos {} is older than the tested version", version);
+        }
+        if let Some(err) = &self.invalid_config {
+            error!(
+                "invalid {}, using defaults: {}",
+                RobotConfig::CONFIG_PATH,
+                err
+            );
+        }
+        if let Some(capacity) = self.low_battery {
+            warn!("battery is low ({:.0}%)", capacity);
+        }
+        for device in self.faults() {
+            if let Some(fault) = device.fault {
+                error!("port {}: {}: {}", device.port, device.name, fault);
+            }
+        }
+    }
+}
@@ -1,25 +1,110 @@
-use embedded_graphics::{pixelcolor::Rgb888, prelude::RgbColor};
-use tinybmp::Bmp;
+use alloc::string::String;
+
 use vexide::{
-    devices::{display::Rect, math::Point2},
+    devices::{
+        display::{Circle, Font, FontFamily, FontSize, Line, Rect, Text},
+        math::Point2,
+        rgb::Rgb,
+    },
+    float::Float as _,
     prelude::Display,
 };
 
-const WIDTH: usize = 480;
-const HEIGHT: usize = 240;
+const LINE_HEIGHT: i16 = 20;
+
+/// Field width in mm.
+pub const FIELD_WIDTH: f64 = 3600.0;
+const TILES: i16 = 6;
+const ROBOT_RADIUS: u16 = 8;
 
-pub fn render_banner(mut display: Display) {
+/// Clears the screen and renders `lines` of monospace text from the top.
+pub fn render_text(display: &mut Display, lines: &[String]) {
     display.erase((0, 0, 0));
-    let bmp = Bmp::<Rgb888>::from_slice(include_bytes!("../assets/brain-image.bmp")).unwrap();
-    display.draw_buffer(
-        Rect {
-            start: Point2 { x: 0, y: 0 },
+    for (i, line) in lines.iter().enumerate() {
+        display.draw_text(
+            &Text::new(
+                line,
+                Font::new(FontSize::SMALL, FontFamily::Monospace),
+                Point2 {
+                    x: 4,
+                    y: 4 + i as i16 * LINE_HEIGHT,
+                },
+            ),
+            (255, 255, 255),
+            None::<Rgb<u8>>,
+        );
+    }
+}
+
+/// Draws the field as a `size` pixel square with its top left corner at
+/// `origin`, with lines between the tiles.
+pub fn render_field(display: &mut Display, origin: Point2<i16>, size: i16) {
+    display.fill(
+        &Rect {
+            start: origin,
             end: Point2 {
-                x: WIDTH as i16,
-                y: HEIGHT as i16,
+                x: origin.x + size,
+                y: origin.y + size,
             },
         },
-        bmp.pixels().map(|p| (p.1.r(), p.1.g(), p.1.b())),
-        WIDTH as i32,
+        (40, 40, 40),
+    );
+    for i in 1..TILES {
+        let offset = i * size / TILES;
+        display.stroke(
+            &Line::new(
+                Point2 {
+                    x: origin.x + offset,
+                    y: origin.y,
+                },
+                Point2 {
+                    x: origin.x + offset,
+                    y: origin.y + size,
+                },
+            ),
+            (90, 90, 90),
+        );
+        display.stroke(
+            &Line::new(
+                Point2 {
+                    x: origin.x,
+                    y: origin.y + offset,
+                },
+                Point2 {
+                    x: origin.x + size,
+                    y: origin.y + offset,
+                },
+            ),
+            (90, 90, 90),
+        );
+    }
+}
+
+/// Converts a field position in mm to screen coordinates on a field drawn by
+/// [`render_field`].
+pub fn field_point(origin: Point2<i16>, size: i16, x: f64, y: f64) -> Point2<i16> {
+    let scale = size as f64 / FIELD_WIDTH;
+    Point2 {
+        x: origin.x + (size as f64 / 2.0 + x * scale) as i16,
+        y: origin.y + (size as f64 / 2.0 - y * scale) as i16,
+    }
+}
+
+/// Draws the robot at `(x, y)` in mm with a line showing its heading.
+pub fn render_robot(
+    display: &mut Display,
+    origin: Point2<i16>,
+    size: i16,
+    (x, y, heading): (f64, f64, f64),
+    color: (u8, u8, u8),
+) {
+    let center = field_point(origin, size, x, y);
+    let nose = field_point(
+        origin,
+        size,
+        x + heading.cos() * FIELD_WIDTH / 24.0,
+        y + heading.sin() * FIELD_WIDTH / 24.0,
     );
+    display.fill(&Circle::new(center, ROBOT_RADIUS), color);
+    display.stroke(&Line::new(center, nose), (255, 255, 255));
 }
@@ -6,42 +6,80 @@
 extern crate alloc;
 
 mod autons;
+mod config;
+// The tuning and no-selector builds use the brain screen for something else
+#[cfg_attr(
+    any(feature = "no_selector", feature = "tune_lady_brown"),
+    allow(dead_code)
+)]
+mod dashboard;
+mod diagnostics;
 mod graphics;
 mod opcontrol;
+#[cfg_attr(
+    any(feature = "no_selector", feature = "tune_lady_brown"),
+    allow(dead_code)
+)]
+mod placement;
+#[cfg_attr(
+    any(feature = "no_selector", feature = "tune_lady_brown"),
+    allow(dead_code)
+)]
+mod selector;
 mod subsystems;
 mod utils;
 
-use alloc::{rc::Rc, vec};
+use alloc::{format, rc::Rc, vec, vec::Vec};
 use core::{cell::RefCell, time::Duration};
 
 use ::autons::prelude::{SelectCompete, SelectCompeteExt};
 use autons::AutonCategory;
 use autons_controller::{route, ControllerSelect};
+use config::{PortConfig, Ports, RobotConfig};
+use dashboard::RouteStep;
+use diagnostics::SelfTest;
 #[cfg(feature = "no_selector")]
 use libdoxa::debug_render::DebugRender;
 use libdoxa::{subsystems::tracking::wheel::TrackingWheel, utils::pose::Pose};
 use log::{error, info};
-use subsystems::{intake::Intake, lady_brown::LadyBrown, Clamp, Doinker, IntakeRaiser};
+use subsystems::{
+    air::{AirBudget, AirLoad, AIR_CONFIG},
+    clamp::GoalSensor,
+    coordinator::Coordinator,
+    inertial::{InertialCalibration, InertialState},
+    intake::{Intake, IntakeSpeeds},
+    lady_brown::{presets::LadyBrownPresets, LadyBrown},
+    motor_monitor::{MonitoredMotor, MotorMonitor, MotorSample},
+    pneumatic::{SinglePneumatic, TimedPneumatic},
+    Clamp, Doinker, IntakeRaiser,
+};
 use utils::logger;
 #[cfg(feature = "no_selector")]
 use vexide::time::Instant;
-use vexide::{prelude::*, startup::banner::themes::THEME_OFFICIAL_LOGO};
+use vexide::{
+    devices::smart::{SmartDeviceType, SmartPort},
+    prelude::*,
+    startup::banner::themes::THEME_OFFICIAL_LOGO,
+};
 use vexide_motorgroup::MotorGroup;
 
-const DRIVETRAIN_CIRCUMFERENCE: f64 = 164.0;
-
 struct Robot {
     controller: Rc<RefCell<Controller>>,
     is_selecting: Rc<RefCell<bool>>,
 
     drivetrain: libdoxa::subsystems::drivetrain::Drivetrain,
     tracking: Rc<RefCell<libdoxa::subsystems::tracking::TrackingSubsystem>>,
+    inertial: InertialCalibration,
 
     intake: Intake,
     intake_raiser: IntakeRaiser,
     doinker: Doinker,
     clamp: Clamp,
+    air: AirBudget,
+    motor_monitor: MotorMonitor,
+    route_step: RouteStep,
     lady_brown: LadyBrown,
+    _coordinator: Coordinator,
 }
 
 impl SelectCompete for Robot {
@@ -49,6 +87,8 @@ impl SelectCompete for Robot {
         self.tracking.borrow_mut().set_reverse(false);
         self.drivetrain.set_max_voltage(Motor::V5_MAX_VOLTAGE);
         self.intake.set_accept(None);
+        self.intake.reset_jam_count();
+        self.route_step.set("driver control");
         info!("Driver starting");
 
         loop {
@@ -60,47 +100,184 @@ impl SelectCompete for Robot {
 
     async fn before_route(&mut self) {
         // self.drivetrain.set_max_voltage(Motor::V5_MAX_VOLTAGE * 0.7);
+        self.intake.reset_jam_count();
+        self.intake.set_speeds(IntakeSpeeds::default());
+        self.clamp.set_auto(true);
+        self.route_step.set("start");
+        // Tracking is useless until the inertial sensor is calibrated
+        if self.inertial.state() == InertialState::Calibrating {
+            info!("waiting for inertial calibration");
+        }
+        if !self.inertial.wait_calibrated().await {
+            error!("inertial sensor isn't calibrated, the route will be off");
+        }
         info!("Auton start");
     }
 
     async fn after_route(&mut self) {
         self.drivetrain.set_max_voltage(Motor::V5_MAX_VOLTAGE);
-        info!("Auton end");
+        self.route_step.set("done");
+        info!("Auton end ({} intake jams)", self.intake.jam_count());
+        // Routes accept their own alliance's rings, so driver control keeps the
+        // same dominant doinker arm
+        if let Some(alliance) = self.intake.accept() {
+            self.doinker.set_alliance(alliance);
+        }
+        self.air.log_summary();
     }
 }
 
+/// Watches one drive motor on its own, so the motor that gets hot is named
+/// instead of its whole side.
+///
+/// The drivetrain's motor groups own the motors and can't be read per motor,
+/// so the readings come straight from the SDK by port. Unlike a second
+/// [`Motor`] on the port, this never writes to the motor.
+fn drive_monitor(side: &str, motor: &PortConfig) -> MonitoredMotor {
+    // SAFETY: the port is only used to check what's plugged into it
+    let port = unsafe { SmartPort::new(motor.port) };
+    // Leaked once at startup, since alerts keep the name for the whole program
+    let name = format!("{} {}", side, motor.port).leak();
+    MonitoredMotor::new(name, true, move || {
+        port.validate_type(SmartDeviceType::Motor).ok()?;
+        // SAFETY: a motor is plugged into the port, and these only read its
+        // status
+        unsafe {
+            let device = vex_sdk::vexDeviceGetByIndex(u32::from(port.number() - 1));
+            Some(MotorSample {
+                temperature: vex_sdk::vexDeviceMotorTemperatureGet(device),
+                current: f64::from(vex_sdk::vexDeviceMotorCurrentGet(device)) / 1000.0,
+            })
+        }
+    })
+}
+
+/// Builds the controller selector's routes from [`autons::with_routes`].
+///
+/// Each route runs through [`selector::controller_route`] with its index in
+/// [`autons::ROUTES`], which is counted up while the list is built, so the
+/// controller's choice is saved too.
+macro_rules! controller_routes {
+    (@routes [$($routes:tt)*] $index:expr;) => {
+        [$($routes)*]
+    };
+    (
+        @routes [$($routes:tt)*] $index:expr;
+        $category:ident, $name:literal, $module:ident::$route:ident, [$($preview:tt)*];
+        $($rest:tt)*
+    ) => {
+        controller_routes!(
+            @routes [
+                $($routes)*
+                route!(AutonCategory::$category, $name, selector::controller_route::<{ $index }>),
+            ]
+            $index + 1;
+            $($rest)*
+        )
+    };
+    ($($entries:tt)*) => {
+        controller_routes!(@routes [] 0; $($entries)*)
+    };
+}
+
 #[vexide::main(banner(theme = THEME_OFFICIAL_LOGO))]
 async fn main(peripherals: Peripherals) {
     logger::init().expect("failed to initialize logger");
 
-    let left_motors = Rc::new(RefCell::new(MotorGroup::new(vec![
-        Motor::new(peripherals.port_5, Gearset::Blue, Direction::Reverse),
-        Motor::new(peripherals.port_6, Gearset::Blue, Direction::Reverse),
-        Motor::new(peripherals.port_17, Gearset::Blue, Direction::Reverse),
-    ])));
-    let right_motors = Rc::new(RefCell::new(MotorGroup::new(vec![
-        Motor::new(peripherals.port_3, Gearset::Blue, Direction::Forward),
-        Motor::new(peripherals.port_9, Gearset::Blue, Direction::Forward),
-        Motor::new(peripherals.port_10, Gearset::Blue, Direction::Forward),
-    ])));
-    let inertial = Rc::new(RefCell::new(InertialSensor::new(peripherals.port_18)));
+    let mut self_test = SelfTest::new();
+    let config = RobotConfig::load().unwrap_or_else(|err| {
+        self_test.config(&err);
+        RobotConfig::defaults()
+    });
+    let mut ports = Ports::new(
+        [
+            peripherals.port_1,
+            peripherals.port_2,
+            peripherals.port_3,
+            peripherals.port_4,
+            peripherals.port_5,
+            peripherals.port_6,
+            peripherals.port_7,
+            peripherals.port_8,
+            peripherals.port_9,
+            peripherals.port_10,
+            peripherals.port_11,
+            peripherals.port_12,
+            peripherals.port_13,
+            peripherals.port_14,
+            peripherals.port_15,
+            peripherals.port_16,
+            peripherals.port_17,
+            peripherals.port_18,
+            peripherals.port_19,
+            peripherals.port_20,
+            peripherals.port_21,
+        ],
+        [
+            peripherals.adi_a,
+            peripherals.adi_b,
+            peripherals.adi_c,
+            peripherals.adi_d,
+            peripherals.adi_e,
+            peripherals.adi_f,
+            peripherals.adi_g,
+            peripherals.adi_h,
+        ],
+    );
+
+    let left_motors = Rc::new(RefCell::new(MotorGroup::new(
+        config
+            .left_drive
+            .iter()
+            .map(|motor| {
+                Motor::new(
+                    ports.smart(motor.port),
+                    config.drive_gearset,
+                    motor.direction,
+                )
+            })
+            .inspect(|motor| self_test.motor("left drive", motor))
+            .collect(),
+    )));
+    let right_motors = Rc::new(RefCell::new(MotorGroup::new(
+        config
+            .right_drive
+            .iter()
+            .map(|motor| {
+                Motor::new(
+                    ports.smart(motor.port),
+                    config.drive_gearset,
+                    motor.direction,
+                )
+            })
+            .inspect(|motor| self_test.motor("right drive", motor))
+            .collect(),
+    )));
+    let inertial = InertialSensor::new(ports.smart(config.inertial));
+    self_test.device("inertial", &inertial);
+    let inertial = Rc::new(RefCell::new(inertial));
+    let tracking_sensor = RotationSensor::new(
+        ports.smart(config.tracking_wheel.port),
+        config.tracking_wheel.direction,
+    );
+    self_test.device("tracking wheel", &tracking_sensor);
     let tracking = Rc::new(RefCell::new(
         libdoxa::subsystems::tracking::TrackingSubsystem::new::<RotationSensor, _, _>(
             [TrackingWheel::new(
-                158.0,
-                43.0,
+                config.tracking_wheel_circumference,
+                config.tracking_wheel_offset,
                 libdoxa::subsystems::tracking::wheel::TrackingWheelMountingDirection::Perpendicular,
-                RotationSensor::new(peripherals.port_19, Direction::Forward), // TODO: verify this is the right port (was 19)
+                tracking_sensor,
             )],
             [
                 TrackingWheel::new(
-                    DRIVETRAIN_CIRCUMFERENCE,
+                    config.drive_circumference,
                     0.0,
                     libdoxa::subsystems::tracking::wheel::TrackingWheelMountingDirection::Parallel,
                     left_motors.clone(),
                 ),
                 TrackingWheel::new(
-                    DRIVETRAIN_CIRCUMFERENCE,
+                    config.drive_circumference,
                     0.0,
                     libdoxa::subsystems::tracking::wheel::TrackingWheelMountingDirection::Parallel,
                     right_motors.clone(),
@@ -110,48 +287,152 @@ async fn main(peripherals: Peripherals) {
             Pose::default(),
         ),
     ));
+    #[cfg(not(any(feature = "no_selector", feature = "tune_lady_brown")))]
+    let placement = placement::StartPlacement::new(
+        inertial.clone(),
+        config
+            .placement_left
+            .map(|port| DistanceSensor::new(ports.smart(port)))
+            .filter(|sensor| self_test.device("placement left", sensor)),
+        config
+            .placement_back
+            .map(|port| DistanceSensor::new(ports.smart(port)))
+            .filter(|sensor| self_test.device("placement back", sensor)),
+    );
+
+    // Calibrates at boot and watches for drift while disabled
+    let inertial_calibration = InertialCalibration::new(inertial.clone());
+
+    let intake_motor = Motor::new(
+        ports.smart(config.intake.port),
+        config.intake_gearset,
+        config.intake.direction,
+    );
+    self_test.motor("intake", &intake_motor);
+    let vision = VisionSensor::new(ports.smart(config.vision));
+    self_test.device("vision", &vision);
+    let optical = OpticalSensor::new(ports.smart(config.optical));
+    self_test.device("optical", &optical);
+    let intake = Intake::new(
+        intake_motor,
+        vision,
+        AdiLineTracker::new(ports.adi(config.line_tracker)),
+        optical,
+    );
+    let lady_brown = LadyBrown::new(
+        MotorGroup::new(
+            config
+                .lady_brown
+                .iter()
+                .map(|motor| Motor::new_exp(ports.smart(motor.port), motor.direction))
+                .inspect(|motor| self_test.motor("lady brown", motor))
+                .collect(),
+        ),
+        config.lady_brown_gear_ratio,
+        AdiDigitalIn::new(ports.adi(config.lady_brown_limit)),
+        LadyBrownPresets::load(),
+    );
+
+    let clamp = Clamp::new(
+        TimedPneumatic::new(
+            SinglePneumatic::new([AdiDigitalOut::new(ports.adi(config.clamp))]),
+            subsystems::CLAMP_TIMING,
+        ),
+        // Without the sensor the clamp falls back to closing after a timeout
+        config
+            .goal_sensor
+            .map(|port| DistanceSensor::new(ports.smart(port)))
+            .filter(|sensor| self_test.device("goal sensor", sensor))
+            .map(GoalSensor::Distance),
+    );
+    let doinker = Doinker::new(
+        TimedPneumatic::new(
+            SinglePneumatic::new([AdiDigitalOut::new(ports.adi(config.doinker_left))]),
+            subsystems::DOINKER_TIMING,
+        ),
+        TimedPneumatic::new(
+            SinglePneumatic::new([AdiDigitalOut::new(ports.adi(config.doinker_right))]),
+            subsystems::DOINKER_TIMING,
+        ),
+        inertial.clone(),
+    );
+    let intake_raiser = IntakeRaiser::new(
+        SinglePneumatic::new([AdiDigitalOut::new(ports.adi(config.intake_raiser))]),
+        subsystems::INTAKE_RAISER_TIMING,
+    );
+
+    self_test.log();
+
+    let mut monitored: Vec<_> = config
+        .left_drive
+        .iter()
+        .map(|motor| drive_monitor("left drive", motor))
+        .chain(
+            config
+                .right_drive
+                .iter()
+                .map(|motor| drive_monitor("right drive", motor)),
+        )
+        .collect();
+    monitored.extend([
+        MonitoredMotor::new("intake", false, {
+            let intake = intake.clone();
+            move || intake.motor_sample()
+        }),
+        MonitoredMotor::new("lady brown", false, {
+            let lady_brown = lady_brown.clone();
+            move || lady_brown.motor_sample()
+        }),
+    ]);
+    let motor_monitor = MotorMonitor::new(monitored);
 
     #[allow(unused_mut)]
     let mut robot = Robot {
         controller: Rc::new(RefCell::new(peripherals.primary_controller)),
         is_selecting: Rc::new(RefCell::new(true)),
 
-        intake_raiser: IntakeRaiser::new([AdiDigitalOut::new(peripherals.adi_c)]),
-
-        doinker: Doinker::new(
-            [AdiDigitalOut::new(peripherals.adi_d)],
-            [AdiDigitalOut::new(peripherals.adi_e)],
-            libdoxa::subsystems::pneumatic::MirroredState::Normal,
-        ),
+        intake_raiser: intake_raiser.clone(),
+        doinker: doinker.clone(),
 
         drivetrain: libdoxa::subsystems::drivetrain::Drivetrain::new(
             left_motors.clone(),
             right_motors.clone(),
-            DRIVETRAIN_CIRCUMFERENCE,
+            config.drive_circumference,
             Motor::V5_MAX_VOLTAGE,
             tracking.clone(),
             3000.0,
         ),
         tracking: tracking.clone(),
+        inertial: inertial_calibration,
 
-        intake: Intake::new(
-            Motor::new(peripherals.port_4, Gearset::Blue, Direction::Reverse),
-            VisionSensor::new(peripherals.port_11),
-            AdiLineTracker::new(peripherals.adi_g),
-            OpticalSensor::new(peripherals.port_15),
-        ),
+        intake: intake.clone(),
 
-        clamp: Clamp::new([AdiDigitalOut::new(peripherals.adi_b)]),
+        clamp: clamp.clone(),
+        air: AirBudget::new(
+            AIR_CONFIG,
+            vec![
+                AirLoad::new("clamp", subsystems::CLAMP_AIR_VOLUME, move || {
+                    clamp.extended()
+                }),
+                AirLoad::new("left doinker", subsystems::DOINKER_AIR_VOLUME, {
+                    let doinker = doinker.clone();
+                    move || doinker.left.extended()
+                }),
+                AirLoad::new("right doinker", subsystems::DOINKER_AIR_VOLUME, move || {
+                    doinker.right.extended()
+                }),
+                AirLoad::new(
+                    "intake raiser",
+                    subsystems::INTAKE_RAISER_AIR_VOLUME,
+                    move || intake_raiser.extended(),
+                ),
+            ],
+        ),
 
-        lady_brown: LadyBrown::new(
-            MotorGroup::new(vec![
-                Motor::new_exp(peripherals.port_1, Direction::Forward),
-                Motor::new_exp(peripherals.port_2, Direction::Reverse),
-            ]),
-            3.0,
-            AdiDigitalIn::new(peripherals.adi_f),
-        )
-        .expect("failed to initialize arm"),
+        motor_monitor,
+        route_step: RouteStep::new(),
+        lady_brown: lady_brown.clone(),
+        _coordinator: Coordinator::new(intake, lady_brown),
     };
 
     info!("entering competing");
@@ -160,15 +441,24 @@ async fn main(peripherals: Peripherals) {
 
     sleep(Duration::from_millis(100)).await;
 
-    #[cfg(feature = "no_selector")]
+    if !self_test.passed() {
+        let mut controller = controller.borrow_mut();
+        _ = controller.try_rumble("---");
+        _ = controller.screen.try_set_text(self_test.summary(), 1, 1);
+    }
+
+    #[cfg(feature = "tune_lady_brown")]
+    {
+        let mut display = peripherals.display;
+        subsystems::lady_brown::tuning::tune(&robot.lady_brown, &mut display).await;
+    }
+    #[cfg(all(feature = "no_selector", not(feature = "tune_lady_brown")))]
     {
         let mut debug_render = DebugRender::new(peripherals.display);
         debug_render.render();
 
         log::info!("No selector feature enabled, running autonomously after inertial calibration");
-        while inertial.borrow().is_calibrating().unwrap() {
-            vexide::time::sleep(Duration::from_millis(100)).await;
-        }
+        robot.inertial.wait_calibrated().await;
         while inertial.borrow().heading().unwrap_or(0.0) == 0.0 {
             vexide::time::sleep(Duration::from_millis(100)).await;
         }
@@ -177,132 +467,36 @@ async fn main(peripherals: Peripherals) {
         // autons::test::red(&mut robot).await;
         log::info!("Auton finished in {:?}", start.elapsed());
     }
-    #[cfg(not(feature = "no_selector"))]
-    {
-        graphics::render_banner(peripherals.display);
+    #[cfg(not(any(feature = "no_selector", feature = "tune_lady_brown")))]
+    let touch_selector = selector::TouchSelector::new(robot.inertial.clone());
+    #[cfg(not(any(feature = "no_selector", feature = "tune_lady_brown")))]
+    let touch_choice = touch_selector.choice();
+    #[cfg(not(any(feature = "no_selector", feature = "tune_lady_brown")))]
+    if let Some(alliance) = touch_selector.alliance() {
+        robot.doinker.set_alliance(alliance);
     }
+    // Without the touchscreen only the controller selects routes
+    #[cfg(any(feature = "no_selector", feature = "tune_lady_brown"))]
+    let touch_choice = selector::TouchChoice::default();
+    #[cfg(not(any(feature = "no_selector", feature = "tune_lady_brown")))]
+    let _dashboard = dashboard::Dashboard::new(
+        peripherals.display,
+        &robot,
+        self_test.lines(),
+        touch_selector,
+        placement,
+    )
+    .spawn();
+    // A route confirmed on the touchscreen takes precedence over the
+    // controller's selection
     robot
-        .compete(ControllerSelect::new(
-            controller.clone(),
-            is_selecting.clone(),
-            [
-                route!(AutonCategory::Test, "Test red", autons::test::red),
-                route!(AutonCategory::Test, "Test blue", autons::test::blue),
-                route!(AutonCategory::Test, "Forward", autons::forward::route),
-                route!(
-                    AutonCategory::RedNegative,
-                    "Rush",
-                    autons::negative_rush::red
-                ),
-                route!(
-                    AutonCategory::BlueNegative,
-                    "Rush",
-                    autons::negative_rush::blue
-                ),
-                route!(
-                    AutonCategory::RedNegative,
-                    "Middle",
-                    autons::negative_middle::red
-                ),
-                route!(
-                    AutonCategory::BlueNegative,
-                    "Middle",
-                    autons::negative_middle::blue
-                ),
-                route!(
-                    AutonCategory::RedNegative,
-                    "Safe",
-                    autons::negative_safe::red
-                ),
-                route!(
-                    AutonCategory::BlueNegative,
-                    "Safe",
-                    autons::negative_safe::blue
-                ),
-                route!(
-                    AutonCategory::RedPositive,
-                    "Rush",
-                    autons::positive_rush::red
-                ),
-                route!(
-                    AutonCategory::BluePositive,
-                    "Rush",
-                    autons::positive_rush::blue
-                ),
-                route!(AutonCategory::RedPositive, "AWP", autons::positive_awp::red),
-                route!(
-                    AutonCategory::BluePositive,
-                    "AWP",
-                    autons::positive_awp::blue
-                ),
-                route!(AutonCategory::RedPositive, "New", autons::positive_new::red),
-                route!(
-                    AutonCategory::BluePositive,
-                    "New",
-                    autons::positive_new::blue
-                ),
-                // All sides autons for elims
-                route!(
-                    AutonCategory::RedPositive,
-                    "Alliance 4*",
-                    autons::alliance_four::red_positive
-                ),
-                route!(
-                    AutonCategory::BluePositive,
-                    "Alliance 4*",
-                    autons::alliance_four::blue_positive
-                ),
-                route!(
-                    AutonCategory::RedNegative,
-                    "Alliance 4*",
-                    autons::alliance_four::red_negative
-                ),
-                route!(
-                    AutonCategory::BlueNegative,
-                    "Alliance 4*",
-                    autons::alliance_four::blue_negative
-                ),
-                route!(
-                    AutonCategory::RedPositive,
-                    "Four*",
-                    autons::four::red_positive
-                ),
-                route!(
-                    AutonCategory::BluePositive,
-                    "Four*",
-                    autons::four::blue_positive
-                ),
-                route!(
-                    AutonCategory::RedNegative,
-                    "Four*",
-                    autons::four::red_negative
-                ),
-                route!(
-                    AutonCategory::BlueNegative,
-                    "Four*",
-                    autons::four::blue_negative
-                ),
-                route!(
-                    AutonCategory::RedPositive,
-                    "Five*",
-                    autons::five::red_positive
-                ),
-                route!(
-                    AutonCategory::BluePositive,
-                    "Five*",
-                    autons::five::blue_positive
-                ),
-                route!(
-                    AutonCategory::RedNegative,
-                    "Five*",
-                    autons::five::red_negative
-                ),
-                route!(
-                    AutonCategory::BlueNegative,
-                    "Five*",
-                    autons::five::blue_negative
-                ),
-            ],
+        .compete(selector::Select::new(
+            ControllerSelect::new(
+                controller.clone(),
+                is_selecting.clone(),
+                autons::with_routes!(controller_routes),
+            ),
+            touch_choice,
         ))
         .await;
 }
@@ -1,10 +1,20 @@
+use alloc::format;
 use core::time::Duration;
 
 use libdoxa::subsystems::drivetrain::DrivetrainPair;
+use log::{info, warn};
 use snafu::Snafu;
-use vexide::prelude::*;
+use vexide::{prelude::*, task::Task, time::Instant};
 
-use crate::{subsystems::lady_brown::LadyBrownState, Robot};
+use crate::{
+    subsystems::{
+        intake::{Intake, IntakeCommand, IntakePriority, IntakeSpeeds},
+        lady_brown::{LadyBrownState, MoveError},
+        CLAMP_AIR_VOLUME,
+    },
+    utils::battery,
+    Robot,
+};
 
 fn curve_drive(input: f64) -> f64 {
     let raw = input.powf(2.0);
@@ -20,12 +30,50 @@ fn curve_turn(input: f64) -> f64 {
     (if input >= 0.0 { raw } else { -raw }) / 2.0
 }
 
+/// Whether the clamp closes by itself when a goal is detected during driver control.
+const AUTO_CLAMP: bool = false;
+
+/// Whether to lower the drive voltage as the drive motors approach their
+/// thermal limit, see [`crate::subsystems::motor_monitor`].
+const DERATE_HOT_DRIVE: bool = true;
+
+/// Moving a stick further than this stops a running corner sweep.
+const SWEEP_CANCEL_STICK: f64 = 0.2;
+
+/// Warn the driver once the clamp can only change state this many more times.
+const AIR_WARNING_CLAMP_ACTUATIONS: u32 = 4;
+
+/// Holding L2 this long moves the lady brown to its first named preset instead
+/// of its next position.
+const PRESET_HOLD_TIME: Duration = Duration::from_millis(300);
+/// While L2 stays held, the lady brown moves on to the next preset this often.
+const PRESET_REPEAT_TIME: Duration = Duration::from_millis(700);
+
+/// Holds the intake for a macro, releasing the macro's priority when the macro
+/// ends or its task is dropped.
+struct MacroIntake(Intake);
+
+impl Drop for MacroIntake {
+    fn drop(&mut self) {
+        self.0.release(IntakePriority::Macro);
+    }
+}
+
 #[derive(Debug, Snafu)]
 pub enum OpcontrolError {}
 
 pub async fn opcontrol(robot: &mut Robot) -> Result<!, OpcontrolError> {
     robot.intake.stop();
+    robot.clamp.set_auto(AUTO_CLAMP);
     let intake_accept = robot.intake.accept();
+    let mut macro_task: Option<Task<()>> = None;
+    let mut sweep_task: Option<Task<()>> = None;
+    let mut arm_overloaded = false;
+    let mut air_warned = false;
+    let mut screen_line = false;
+    let mut feeding_arm = None;
+    // When L2 was pressed or last moved to a preset, and whether it has moved to one
+    let mut l2_held: Option<(Instant, bool)> = None;
     loop {
         if *robot.is_selecting.borrow() {
             sleep(Duration::from_millis(10)).await;
@@ -39,27 +87,69 @@ pub async fn opcontrol(robot: &mut Robot) -> Result<!, OpcontrolError> {
         let left_percent = (speed + turn).clamp(-1.0, 1.0);
         let right_percent = (speed - turn).clamp(-1.0, 1.0);
 
-        robot.drivetrain.set_voltage(DrivetrainPair {
-            left: Motor::V5_MAX_VOLTAGE * left_percent,
-            right: Motor::V5_MAX_VOLTAGE * right_percent,
-            units: libdoxa::subsystems::drivetrain::drivetrain_pair::DrivetrainUnits::Voltage,
-        });
+        // Taking the sticks cancels a corner sweep
+        if state.left_stick.y().abs() > SWEEP_CANCEL_STICK
+            || state.right_stick.x().abs() > SWEEP_CANCEL_STICK
+        {
+            sweep_task.take();
+        }
+        let compensation = battery::compensation();
+        if let Some(voltage) = robot.doinker.sweep_voltage() {
+            robot.drivetrain.set_voltage(DrivetrainPair {
+                left: voltage * compensation,
+                right: -voltage * compensation,
+                units: libdoxa::subsystems::drivetrain::drivetrain_pair::DrivetrainUnits::Voltage,
+            });
+        } else {
+            robot.drivetrain.set_voltage(DrivetrainPair {
+                left: (Motor::V5_MAX_VOLTAGE * left_percent * compensation)
+                    .clamp(-Motor::V5_MAX_VOLTAGE, Motor::V5_MAX_VOLTAGE),
+                right: (Motor::V5_MAX_VOLTAGE * right_percent * compensation)
+                    .clamp(-Motor::V5_MAX_VOLTAGE, Motor::V5_MAX_VOLTAGE),
+                units: libdoxa::subsystems::drivetrain::drivetrain_pair::DrivetrainUnits::Voltage,
+            });
+        }
+
+        // Only switch profiles when the arm changes so speeds set elsewhere stick
+        let feeding = matches!(robot.lady_brown.state(), LadyBrownState::Intake);
+        if feeding_arm != Some(feeding) {
+            feeding_arm = Some(feeding);
+            robot.intake.set_speeds(if feeding {
+                IntakeSpeeds::LADY_BROWN
+            } else {
+                IntakeSpeeds::MOBILE_GOAL
+            });
+        }
 
+        if state.button_r1.is_now_pressed() || state.button_l1.is_now_pressed() {
+            // Driver input cancels any running macro, which releases its
+            // intake priority. The driver's request also retries the intake
+            // after a jam give-up.
+            macro_task.take();
+        }
         if state.button_r1.is_now_pressed() {
-            robot.intake.run(Direction::Forward);
+            robot
+                .intake
+                .request(IntakePriority::Driver, IntakeCommand::Forward);
         }
         if state.button_l1.is_now_pressed() {
-            robot.intake.run(Direction::Reverse);
+            robot
+                .intake
+                .request(IntakePriority::Driver, IntakeCommand::Reverse);
         }
         if state.button_r1.is_now_released() || state.button_l1.is_now_released() {
-            if matches!(robot.lady_brown.state(), LadyBrownState::Intake) {
-                robot.intake.stop_hold();
-            } else {
-                robot.intake.stop();
-            }
+            robot.intake.release(IntakePriority::Driver);
+            // The coordinator holds the belt if the arm is loading
+            robot.intake.stop();
         }
 
-        if state.button_y.is_now_pressed() {
+        if state.button_y.is_now_pressed() && state.button_r2.is_pressed() {
+            let mut doinker = robot.doinker.clone();
+            // Dropping the previous task cancels it
+            sweep_task = Some(spawn(async move {
+                doinker.clear_corner_in_task().await;
+            }));
+        } else if state.button_y.is_now_pressed() {
             if robot.doinker.left.extended() {
                 robot.doinker.left.retract();
             } else {
@@ -81,22 +171,67 @@ pub async fn opcontrol(robot: &mut Robot) -> Result<!, OpcontrolError> {
             robot.intake_raiser.retract();
         }
 
+        // Tapping L2 steps through the positions, holding it cycles the presets
         if state.button_l2.is_now_pressed() {
-            match robot.lady_brown.state() {
-                LadyBrownState::Initial => robot.lady_brown.set_state(LadyBrownState::Intake),
-                LadyBrownState::Intake => {
-                    robot.intake.stop();
-                    robot.lady_brown.set_state(LadyBrownState::MaxExpansion);
+            l2_held = Some((Instant::now(), false));
+        }
+        match l2_held {
+            Some((since, cycling)) if state.button_l2.is_pressed() => {
+                let hold = if cycling {
+                    PRESET_REPEAT_TIME
+                } else {
+                    PRESET_HOLD_TIME
+                };
+                if since.elapsed() >= hold {
+                    if let Some(preset) = robot.lady_brown.presets().next(robot.lady_brown.state())
+                    {
+                        info!(
+                            "lady brown preset: {}",
+                            robot.lady_brown.presets().name(preset).unwrap_or_default()
+                        );
+                        robot.lady_brown.set_state(preset);
+                    }
+                    l2_held = Some((Instant::now(), true));
                 }
-                LadyBrownState::MaxExpansion => robot.lady_brown.set_state(LadyBrownState::Initial),
-                LadyBrownState::Manual(_) => robot.lady_brown.set_state(LadyBrownState::Initial),
             }
+            Some((_, cycling)) => {
+                if !cycling {
+                    match robot.lady_brown.state() {
+                        LadyBrownState::Initial => {
+                            robot.lady_brown.set_state(LadyBrownState::Intake)
+                        }
+                        LadyBrownState::Intake => {
+                            robot.lady_brown.set_state(LadyBrownState::MaxExpansion)
+                        }
+                        LadyBrownState::MaxExpansion => {
+                            robot.lady_brown.set_state(LadyBrownState::Initial)
+                        }
+                        LadyBrownState::Manual(_) | LadyBrownState::Preset { .. } => {
+                            robot.lady_brown.set_state(LadyBrownState::Initial)
+                        }
+                    }
+                }
+                l2_held = None;
+            }
+            None => {}
         }
-        if state.button_up.is_pressed() {
-            robot.lady_brown.manual_add(2.0);
-        }
-        if state.button_down.is_pressed() {
-            robot.lady_brown.manual_add(-2.0);
+        let home_chord = state.button_up.is_pressed() && state.button_down.is_pressed();
+        if home_chord && (state.button_up.is_now_pressed() || state.button_down.is_now_pressed()) {
+            let lady_brown = robot.lady_brown.clone();
+            // Dropping the previous task cancels it
+            macro_task = Some(spawn(async move {
+                match lady_brown.home().await {
+                    Ok(drift) => info!("lady brown homed, corrected {:.1} deg", drift),
+                    Err(err) => warn!("lady brown homing failed: {}", err),
+                }
+            }));
+        } else if !home_chord {
+            if state.button_up.is_pressed() {
+                robot.lady_brown.manual_add(2.0);
+            }
+            if state.button_down.is_pressed() {
+                robot.lady_brown.manual_add(-2.0);
+            }
         }
 
         if state.button_b.is_now_pressed() {
@@ -113,38 +248,98 @@ pub async fn opcontrol(robot: &mut Robot) -> Result<!, OpcontrolError> {
 
         // Macros
         if state.button_left.is_now_pressed() {
-            let intake = robot.intake.clone();
+            let intake = MacroIntake(robot.intake.clone());
             let lady_brown = robot.lady_brown.clone();
-            spawn(async move {
-                intake.stop();
-                lady_brown.set_state(LadyBrownState::MaxExpansion);
-                sleep(Duration::from_millis(800)).await;
-                lady_brown.set_state(LadyBrownState::Intake);
-                sleep(Duration::from_millis(300)).await;
-                intake.run(Direction::Forward);
-                sleep(Duration::from_millis(600)).await;
-                intake.stop();
-                lady_brown.set_state(LadyBrownState::MaxExpansion);
-            })
-            .detach();
-        }
-        if state.button_r2.is_now_pressed() {
-            robot
-                .drivetrain
-                .set_max_voltage(Motor::V5_MAX_VOLTAGE * 0.8);
-        } else if state.button_r2.is_now_released() {
-            robot.drivetrain.set_max_voltage(Motor::V5_MAX_VOLTAGE);
+            // Dropping the previous task cancels it
+            macro_task = Some(spawn(async move {
+                intake.0.request(IntakePriority::Macro, IntakeCommand::Stop);
+                let result = async {
+                    lady_brown.move_to(LadyBrownState::MaxExpansion).await?;
+                    lady_brown.move_to(LadyBrownState::Intake).await?;
+                    intake
+                        .0
+                        .request(IntakePriority::Macro, IntakeCommand::Forward);
+                    sleep(Duration::from_millis(600)).await;
+                    intake.0.request(IntakePriority::Macro, IntakeCommand::Stop);
+                    lady_brown.set_state(LadyBrownState::MaxExpansion);
+                    Ok::<(), MoveError>(())
+                }
+                .await;
+                if let Err(err) = result {
+                    log::warn!("lady brown macro aborted: {}", err);
+                }
+            }));
+        }
+        let speed_limit = if state.button_r2.is_pressed() {
+            0.8
+        } else {
+            1.0
+        };
+        let derate = if DERATE_HOT_DRIVE {
+            robot.motor_monitor.drive_derate()
+        } else {
+            1.0
+        };
+        robot
+            .drivetrain
+            .set_max_voltage(Motor::V5_MAX_VOLTAGE * speed_limit * derate);
+
+        if robot.motor_monitor.take_alert().is_some() {
+            _ = robot.controller.borrow_mut().try_rumble("-..-");
         }
 
-        _ = robot.controller.borrow_mut().screen.try_set_text(
-            if robot.intake.accept().is_some() {
-                "Intake: Accepting         "
-            } else {
-                "Intake: Rejecting         "
-            },
-            1,
-            1,
-        );
+        if robot.intake.take_jam_alert() {
+            _ = robot.controller.borrow_mut().try_rumble("---");
+        }
+        if robot.lady_brown.is_overloaded() != arm_overloaded {
+            arm_overloaded = !arm_overloaded;
+            if arm_overloaded {
+                _ = robot.controller.borrow_mut().try_rumble("..");
+            }
+        }
+
+        let clamp_actuations = robot.air.remaining_actuations(CLAMP_AIR_VOLUME);
+        if clamp_actuations <= AIR_WARNING_CLAMP_ACTUATIONS && !air_warned {
+            air_warned = true;
+            warn!("air low, {} clamp actuations left", clamp_actuations);
+            _ = robot.controller.borrow_mut().try_rumble("-.-.");
+        }
+
+        // Alternate lines since the controller only accepts one write at a time
+        screen_line = !screen_line;
+        if screen_line && let Some((motor, temperature)) = robot.motor_monitor.hottest() {
+            _ = robot.controller.borrow_mut().screen.try_set_text(
+                format!("HOT: {} {:.0}C        ", motor, temperature),
+                1,
+                1,
+            );
+        } else if screen_line {
+            _ = robot.controller.borrow_mut().screen.try_set_text(
+                format!(
+                    "{} | Clamp: {}      ",
+                    if !robot.intake.capabilities().color_sorting {
+                        "No sort"
+                    } else if robot.intake.accept().is_some() {
+                        "Accept"
+                    } else {
+                        "Reject"
+                    },
+                    robot.clamp.state()
+                ),
+                1,
+                1,
+            );
+        } else {
+            _ = robot.controller.borrow_mut().screen.try_set_text(
+                format!(
+                    "Air: {:.0} psi ({} clamp)   ",
+                    robot.air.pressure(),
+                    clamp_actuations
+                ),
+                2,
+                1,
+            );
+        }
 
         sleep(Duration::from_millis(10)).await;
     }
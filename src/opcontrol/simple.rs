@@ -4,7 +4,10 @@ use libdoxa::subsystems::drivetrain::VoltagePair;
 use snafu::Snafu;
 use vexide::prelude::*;
 
-use crate::{subsystems::lady_brown::LadyBrownState, Robot};
+use crate::{
+    subsystems::{intake::IntakeSpeeds, lady_brown::LadyBrownState},
+    Robot,
+};
 
 fn curve_drive(input: f64) -> f64 {
     let raw = input.powf(2.0);
@@ -25,6 +28,7 @@ pub enum OpcontrolError {}
 
 pub async fn opcontrol(robot: &mut Robot) -> Result<!, OpcontrolError> {
     robot.intake.stop();
+    let mut feeding_arm = None;
     loop {
         let state = robot
             .controller
@@ -44,15 +48,23 @@ pub async fn opcontrol(robot: &mut Robot) -> Result<!, OpcontrolError> {
             units: libdoxa::subsystems::drivetrain::drivetrain_pair::DrivetrainUnits::RPM,
         });
 
+        // Only switch profiles when the arm changes so speeds set elsewhere stick
+        let feeding = matches!(robot.lady_brown.state(), LadyBrownState::Intake);
+        if feeding_arm != Some(feeding) {
+            feeding_arm = Some(feeding);
+            robot.intake.set_speeds(if feeding {
+                IntakeSpeeds::LADY_BROWN
+            } else {
+                IntakeSpeeds::MOBILE_GOAL
+            });
+        }
+
         if state.button_a.is_now_pressed() {
             robot.intake.run(Direction::Forward);
         }
         if state.button_a.is_now_released() {
-            if matches!(robot.lady_brown.state(), LadyBrownState::Intake) {
-                robot.intake.stop_hold();
-            } else {
-                robot.intake.stop();
-            }
+            // The coordinator holds the belt if the arm is loading
+            robot.intake.stop();
         }
 
         if state.button_l2.is_now_pressed() {
@@ -78,12 +90,11 @@ pub async fn opcontrol(robot: &mut Robot) -> Result<!, OpcontrolError> {
         if state.button_right.is_now_pressed() {
             match robot.lady_brown.state() {
                 LadyBrownState::Initial => robot.lady_brown.set_state(LadyBrownState::Intake),
-                LadyBrownState::Intake => {
-                    robot.intake.stop();
-                    robot.lady_brown.set_state(LadyBrownState::MaxExpansion);
-                }
+                LadyBrownState::Intake => robot.lady_brown.set_state(LadyBrownState::MaxExpansion),
                 LadyBrownState::MaxExpansion => robot.lady_brown.set_state(LadyBrownState::Initial),
-                LadyBrownState::Manual(_) => robot.lady_brown.set_state(LadyBrownState::Initial),
+                LadyBrownState::Manual(_) | LadyBrownState::Preset { .. } => {
+                    robot.lady_brown.set_state(LadyBrownState::Initial)
+                }
             }
         }
         if state.button_left.is_now_pressed() {
@@ -92,7 +103,9 @@ pub async fn opcontrol(robot: &mut Robot) -> Result<!, OpcontrolError> {
                 LadyBrownState::MaxExpansion => robot.lady_brown.set_state(LadyBrownState::Intake),
 
                 LadyBrownState::Intake => robot.lady_brown.set_state(LadyBrownState::Initial),
-                LadyBrownState::Manual(_) => robot.lady_brown.set_state(LadyBrownState::Initial),
+                LadyBrownState::Manual(_) | LadyBrownState::Preset { .. } => {
+                    robot.lady_brown.set_state(LadyBrownState::Initial)
+                }
             }
         }
         if state.button_up.is_pressed() {
@@ -106,6 +119,10 @@ pub async fn opcontrol(robot: &mut Robot) -> Result<!, OpcontrolError> {
             robot.clamp.toggle();
         }
 
+        if robot.intake.take_jam_alert() {
+            _ = robot.controller.borrow_mut().try_rumble("---");
+        }
+
         sleep(Duration::from_millis(10));
     }
 }
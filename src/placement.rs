@@ -0,0 +1,159 @@
+use alloc::rc::Rc;
+use core::{
+    cell::RefCell,
+    f64::consts::{FRAC_PI_2, PI},
+    fmt,
+};
+
+use vexide::{
+    float::Float as _,
+    prelude::{DistanceSensor, InertialSensor},
+};
+
+use crate::{autons::RoutePreview, graphics::FIELD_WIDTH};
+
+/// Largest heading change (in degrees) since calibration that still counts as
+/// correctly placed.
+const HEADING_TOLERANCE: f64 = 3.0;
+/// Largest difference (in mm) between a measured and expected wall distance
+/// that still counts as correctly placed.
+const DISTANCE_TOLERANCE: f64 = 40.0;
+
+/// Where a placement distance sensor is mounted on the robot.
+struct Mounting {
+    /// Offset from the tracking center in mm, as (forward, left).
+    offset: (f64, f64),
+    /// Direction the sensor faces relative to the front of the robot, in
+    /// radians counterclockwise.
+    direction: f64,
+}
+
+const LEFT_MOUNTING: Mounting = Mounting {
+    offset: (0.0, 160.0),
+    direction: FRAC_PI_2,
+};
+const BACK_MOUNTING: Mounting = Mounting {
+    offset: (-170.0, 0.0),
+    direction: PI,
+};
+
+/// Returns the distance from `(x, y)` to the field perimeter along `angle`.
+fn distance_to_wall(x: f64, y: f64, angle: f64) -> f64 {
+    let half = FIELD_WIDTH / 2.0;
+    let (dx, dy) = (angle.cos(), angle.sin());
+    let mut distance = f64::INFINITY;
+    if dx.abs() > 1e-6 {
+        let wall = if dx > 0.0 { half } else { -half };
+        distance = distance.min((wall - x) / dx);
+    }
+    if dy.abs() > 1e-6 {
+        let wall = if dy > 0.0 { half } else { -half };
+        distance = distance.min((wall - y) / dy);
+    }
+    distance
+}
+
+/// Result of checking the robot's placement against a route's start pose.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlacementStatus {
+    /// Nothing could be measured, e.g. without a distance sensor reading.
+    Unchecked,
+    Ok,
+    Off {
+        /// The robot was turned since the inertial sensor calibrated.
+        turned: bool,
+        /// A wall is closer or further than expected.
+        moved: bool,
+    },
+}
+
+impl fmt::Display for PlacementStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlacementStatus::Unchecked => f.write_str("Start: unchecked"),
+            PlacementStatus::Ok => f.write_str("Start: OK"),
+            PlacementStatus::Off { turned, moved } => write!(
+                f,
+                "Start: {}",
+                match (turned, moved) {
+                    (true, true) => "turned, moved",
+                    (true, false) => "turned",
+                    _ => "moved",
+                }
+            ),
+        }
+    }
+}
+
+/// Checks that the robot sits on a route's starting pose before autonomous.
+///
+/// The optional distance sensors measure the distance to the field perimeter,
+/// which is compared against where the walls should be from the start pose.
+/// They're the only absolute reference, so nothing is checked without a
+/// reading. The inertial sensor only knows how far the robot turned since it
+/// calibrated, which should stay near zero once the robot is placed.
+pub struct StartPlacement {
+    inertial: Rc<RefCell<InertialSensor>>,
+    left: Option<DistanceSensor>,
+    back: Option<DistanceSensor>,
+}
+
+impl StartPlacement {
+    pub fn new(
+        inertial: Rc<RefCell<InertialSensor>>,
+        left: Option<DistanceSensor>,
+        back: Option<DistanceSensor>,
+    ) -> Self {
+        Self {
+            inertial,
+            left,
+            back,
+        }
+    }
+
+    /// Returns how far the heading is from where it was calibrated, in
+    /// degrees.
+    fn heading_error(&self) -> Option<f64> {
+        let inertial = self.inertial.borrow();
+        if inertial.is_calibrating().unwrap_or(true) {
+            return None;
+        }
+        let heading = inertial.heading().ok()?;
+        Some(if heading > 180.0 {
+            360.0 - heading
+        } else {
+            heading
+        })
+    }
+
+    /// Returns the largest difference between a measured and expected wall
+    /// distance, in mm.
+    fn distance_error(&self, (x, y, heading): (f64, f64, f64)) -> Option<f64> {
+        [(&self.left, LEFT_MOUNTING), (&self.back, BACK_MOUNTING)]
+            .into_iter()
+            .filter_map(|(sensor, mounting)| {
+                let object = sensor.as_ref()?.object().ok()??;
+                let (forward, left) = mounting.offset;
+                let sensor_x = x + forward * heading.cos() - left * heading.sin();
+                let sensor_y = y + forward * heading.sin() + left * heading.cos();
+                let expected = distance_to_wall(sensor_x, sensor_y, heading + mounting.direction);
+                Some((object.distance as f64 - expected).abs())
+            })
+            .reduce(f64::max)
+    }
+
+    pub fn check(&self, preview: &RoutePreview) -> PlacementStatus {
+        let Some(distance_error) = self.distance_error(preview.start_pose()) else {
+            return PlacementStatus::Unchecked;
+        };
+        let turned = self
+            .heading_error()
+            .is_some_and(|error| error > HEADING_TOLERANCE);
+        let moved = distance_error > DISTANCE_TOLERANCE;
+        if turned || moved {
+            PlacementStatus::Off { turned, moved }
+        } else {
+            PlacementStatus::Ok
+        }
+    }
+}
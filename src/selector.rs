@@ -0,0 +1,421 @@
+use alloc::{
+    format,
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{
+    cell::Cell,
+    ptr,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use ::autons::Selector;
+use log::{info, warn};
+use vexide::{
+    devices::{
+        display::{Font, FontFamily, FontSize, Line, Rect, Text},
+        math::Point2,
+        rgb::Rgb,
+    },
+    prelude::Display,
+};
+
+use crate::{
+    autons::{AutonCategory, AutonRoute, RoutePreview, ROUTES},
+    graphics,
+    placement::PlacementStatus,
+    subsystems::{
+        inertial::{InertialCalibration, InertialState},
+        intake::RingColor,
+    },
+    Robot,
+};
+
+const CATEGORY_WIDTH: i16 = 80;
+const CATEGORY_HEIGHT: i16 = 40;
+const ROUTE_X: i16 = 84;
+const ROUTE_WIDTH: i16 = 152;
+const ROUTE_HEIGHT: i16 = 30;
+const PREVIEW_ORIGIN: Point2<i16> = Point2 { x: 264, y: 0 };
+const PREVIEW_SIZE: i16 = 192;
+const INERTIAL_BUTTON: Rect = Rect {
+    start: Point2 { x: ROUTE_X, y: 200 },
+    end: Point2 {
+        x: ROUTE_X + ROUTE_WIDTH,
+        y: 236,
+    },
+};
+const BUTTON: Rect = Rect {
+    start: Point2 { x: 244, y: 200 },
+    end: Point2 { x: 476, y: 236 },
+};
+
+const HIGHLIGHT: (u8, u8, u8) = (230, 200, 90);
+const CONFIRMED: (u8, u8, u8) = (80, 180, 90);
+const IDLE: (u8, u8, u8) = (60, 60, 60);
+const MISPLACED: (u8, u8, u8) = (210, 60, 50);
+
+/// File on the SD card the selected route and its alliance are saved to, so
+/// they're kept if the program restarts between matches.
+const SAVED_ROUTE_PATH: &str = "auton.cfg";
+
+/// Index in [`ROUTES`] of the route the controller selector last ran, plus
+/// one, or zero if it hasn't run one.
+static CONTROLLER_ROUTE: AtomicUsize = AtomicUsize::new(0);
+
+/// The route confirmed on the touchscreen, shared with [`Select`].
+pub type TouchChoice = Rc<Cell<Option<&'static AutonRoute>>>;
+
+fn contains(rect: &Rect, point: Point2<i16>) -> bool {
+    (rect.start.x..rect.end.x).contains(&point.x) && (rect.start.y..rect.end.y).contains(&point.y)
+}
+
+/// Saves the selected route and its alliance to [`SAVED_ROUTE_PATH`], or
+/// clears the file.
+fn save(route: Option<&AutonRoute>) {
+    let mut contents = String::new();
+    if let Some(route) = route {
+        contents = format!("category = {:?}\nroute = {}\n", route.category, route.name);
+        if let Some(alliance) = route.category.alliance() {
+            contents += &format!("alliance = {:?}\n", alliance);
+        }
+    }
+    if let Err(err) = vexide::fs::write(SAVED_ROUTE_PATH, contents) {
+        warn!("failed to save the selected route: {:?}", err);
+    }
+}
+
+/// Loads the route and alliance saved by [`save`], if there are any. The route
+/// is skipped if it doesn't exist anymore.
+fn load() -> (Option<&'static AutonRoute>, Option<RingColor>) {
+    let Ok(source) = vexide::fs::read_to_string(SAVED_ROUTE_PATH) else {
+        return (None, None);
+    };
+    let mut category = None;
+    let mut name = None;
+    let mut alliance = None;
+    for line in source.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        match key.trim() {
+            "category" => category = Some(value.trim()),
+            "route" => name = Some(value.trim()),
+            "alliance" => {
+                alliance = [RingColor::Red, RingColor::Blue]
+                    .into_iter()
+                    .find(|alliance| format!("{:?}", alliance) == value.trim())
+            }
+            _ => {}
+        }
+    }
+    let route = category.zip(name).and_then(|(category, name)| {
+        let route = ROUTES
+            .iter()
+            .find(|route| format!("{:?}", route.category) == category && route.name == name);
+        if route.is_none() {
+            warn!("saved route {} {} doesn't exist anymore", category, name);
+        }
+        route
+    });
+    (route, alliance)
+}
+
+/// Runs the `I`th route of [`ROUTES`] for the controller selector, saving it
+/// first so the controller's choice is restored if the program restarts.
+pub async fn controller_route<const I: usize>(robot: &mut Robot) {
+    let route = &ROUTES[I];
+    CONTROLLER_ROUTE.store(I + 1, Ordering::Relaxed);
+    save(Some(route));
+    (route.run)(robot).await;
+}
+
+/// Route selector on the brain screen, drawn by the dashboard while the robot
+/// is disabled.
+///
+/// A category is picked on the left, then a route in the middle, which shows
+/// its starting pose and path on the field preview. The button under the
+/// preview confirms the route, or clears it when it's already confirmed.
+///
+/// The inertial sensor's calibration is shown under the routes, and tapping it
+/// calibrates the sensor again.
+///
+/// The confirmed route is saved to the SD card and highlighted again when the
+/// program starts, so it only needs confirming. Routes run from the controller
+/// are saved the same way, but a restored route isn't confirmed by itself so it
+/// never overrides the next controller selection.
+pub struct TouchSelector {
+    category: AutonCategory,
+    highlighted: Option<&'static AutonRoute>,
+    confirmed: TouchChoice,
+    /// Whether the highlighted route was restored from the SD card.
+    restored: bool,
+    /// Alliance restored from the SD card.
+    alliance: Option<RingColor>,
+    inertial: InertialCalibration,
+}
+
+impl TouchSelector {
+    pub fn new(inertial: InertialCalibration) -> Self {
+        let (saved, alliance) = load();
+        if let Some(route) = saved {
+            info!(
+                "restored {} {} from {}",
+                route.category, route.name, SAVED_ROUTE_PATH
+            );
+        }
+        if let Some(alliance) = alliance {
+            info!("restored {:?} alliance from {}", alliance, SAVED_ROUTE_PATH);
+        }
+        Self {
+            category: saved.map_or(AutonCategory::RedPositive, |route| route.category),
+            highlighted: saved,
+            confirmed: Rc::new(Cell::new(None)),
+            restored: saved.is_some(),
+            alliance,
+            inertial,
+        }
+    }
+
+    pub fn choice(&self) -> TouchChoice {
+        self.confirmed.clone()
+    }
+
+    /// Returns the alliance saved with the last selected route.
+    pub fn alliance(&self) -> Option<RingColor> {
+        self.alliance
+    }
+
+    /// Returns the preview of the highlighted route, if it has one.
+    pub fn preview(&self) -> Option<RoutePreview> {
+        self.highlighted.and_then(|route| route.preview)
+    }
+
+    /// Returns the route that runs next: the confirmed one, or else the one the
+    /// controller selector last ran, since its choice is only known once it
+    /// runs.
+    pub fn next_route(&self) -> Option<&'static AutonRoute> {
+        self.confirmed.get().or_else(|| {
+            CONTROLLER_ROUTE
+                .load(Ordering::Relaxed)
+                .checked_sub(1)
+                .map(|index| &ROUTES[index])
+        })
+    }
+
+    fn routes(&self) -> impl Iterator<Item = &'static AutonRoute> + '_ {
+        ROUTES
+            .iter()
+            .filter(|route| route.category == self.category)
+    }
+
+    fn is_confirmed(&self, route: &'static AutonRoute) -> bool {
+        self.confirmed
+            .get()
+            .is_some_and(|confirmed| ptr::eq(confirmed, route))
+    }
+
+    /// Handles a press on the screen. Returns whether anything changed.
+    pub fn press(&mut self, point: Point2<i16>) -> bool {
+        if point.x < CATEGORY_WIDTH {
+            let Some(category) = AutonCategory::ALL.get((point.y / CATEGORY_HEIGHT) as usize)
+            else {
+                return false;
+            };
+            self.category = *category;
+            self.highlighted = None;
+            self.restored = false;
+            true
+        } else if contains(&INERTIAL_BUTTON, point) {
+            self.inertial.recalibrate();
+            true
+        } else if (ROUTE_X..ROUTE_X + ROUTE_WIDTH).contains(&point.x) {
+            let Some(route) = self.routes().nth((point.y / ROUTE_HEIGHT) as usize) else {
+                return false;
+            };
+            self.highlighted = Some(route);
+            self.restored = false;
+            true
+        } else if contains(&BUTTON, point) {
+            let Some(route) = self.highlighted else {
+                return false;
+            };
+            if self.is_confirmed(route) {
+                info!("touchscreen selection cleared");
+                self.confirmed.set(None);
+            } else {
+                info!(
+                    "selected {} {} on the touchscreen",
+                    route.category, route.name
+                );
+                self.confirmed.set(Some(route));
+            }
+            self.restored = false;
+            save(self.confirmed.get());
+            true
+        } else {
+            false
+        }
+    }
+
+    fn draw_label(display: &mut Display, label: &str, x: i16, y: i16) {
+        display.draw_text(
+            &Text::new(
+                label,
+                Font::new(FontSize::SMALL, FontFamily::Monospace),
+                Point2 { x, y },
+            ),
+            (255, 255, 255),
+            None::<Rgb<u8>>,
+        );
+    }
+
+    pub fn render(&self, display: &mut Display, placement: PlacementStatus) {
+        display.erase((0, 0, 0));
+
+        for (i, category) in AutonCategory::ALL.iter().enumerate() {
+            let y = i as i16 * CATEGORY_HEIGHT;
+            display.fill(
+                &Rect {
+                    start: Point2 { x: 0, y: y + 1 },
+                    end: Point2 {
+                        x: CATEGORY_WIDTH,
+                        y: y + CATEGORY_HEIGHT - 1,
+                    },
+                },
+                if *category == self.category {
+                    HIGHLIGHT
+                } else {
+                    IDLE
+                },
+            );
+            Self::draw_label(display, &category.to_string(), 6, y + 10);
+        }
+
+        let routes: Vec<_> = self.routes().collect();
+        if routes.is_empty() {
+            Self::draw_label(display, "No routes", ROUTE_X + 6, 6);
+        }
+        for (i, route) in routes.into_iter().enumerate() {
+            let y = i as i16 * ROUTE_HEIGHT;
+            let color = if self.is_confirmed(route) {
+                Some(CONFIRMED)
+            } else if self
+                .highlighted
+                .is_some_and(|highlighted| ptr::eq(highlighted, route))
+            {
+                Some(HIGHLIGHT)
+            } else {
+                None
+            };
+            if let Some(color) = color {
+                display.fill(
+                    &Rect {
+                        start: Point2 {
+                            x: ROUTE_X,
+                            y: y + 1,
+                        },
+                        end: Point2 {
+                            x: ROUTE_X + ROUTE_WIDTH,
+                            y: y + ROUTE_HEIGHT - 1,
+                        },
+                    },
+                    color,
+                );
+            }
+            Self::draw_label(display, route.name, ROUTE_X + 6, y + 6);
+        }
+
+        let inertial = self.inertial.state();
+        display.fill(
+            &INERTIAL_BUTTON,
+            match inertial {
+                InertialState::Ready => IDLE,
+                InertialState::Calibrating => HIGHLIGHT,
+                InertialState::Failed | InertialState::Drifting => MISPLACED,
+            },
+        );
+        Self::draw_label(
+            display,
+            &format!("IMU: {}", inertial),
+            INERTIAL_BUTTON.start.x + 6,
+            INERTIAL_BUTTON.start.y + 10,
+        );
+
+        graphics::render_field(display, PREVIEW_ORIGIN, PREVIEW_SIZE);
+        if let Some(preview) = self.preview() {
+            let (x, y, heading) = preview.start_pose();
+            let mut last = graphics::field_point(PREVIEW_ORIGIN, PREVIEW_SIZE, x, y);
+            for (x, y) in preview.path_points() {
+                let point = graphics::field_point(PREVIEW_ORIGIN, PREVIEW_SIZE, x, y);
+                display.stroke(&Line::new(last, point), HIGHLIGHT);
+                last = point;
+            }
+            // The placement is checked against the route that runs next
+            let placement = match (self.highlighted, self.next_route()) {
+                (Some(highlighted), Some(next)) if ptr::eq(highlighted, next) => placement,
+                _ => PlacementStatus::Unchecked,
+            };
+            graphics::render_robot(
+                display,
+                PREVIEW_ORIGIN,
+                PREVIEW_SIZE,
+                (x, y, heading),
+                match placement {
+                    PlacementStatus::Unchecked => HIGHLIGHT,
+                    PlacementStatus::Ok => CONFIRMED,
+                    PlacementStatus::Off { .. } => MISPLACED,
+                },
+            );
+            Self::draw_label(
+                display,
+                &placement.to_string(),
+                PREVIEW_ORIGIN.x + 4,
+                PREVIEW_ORIGIN.y + 4,
+            );
+        }
+
+        let (label, color) = match (self.highlighted, self.confirmed.get()) {
+            (Some(route), _) if self.is_confirmed(route) => {
+                (format!("{} (tap to clear)", route.name), CONFIRMED)
+            }
+            (Some(route), _) if self.restored => {
+                (format!("Confirm restored {}", route.name), HIGHLIGHT)
+            }
+            (Some(route), _) => (format!("Confirm {}", route.name), HIGHLIGHT),
+            (None, Some(confirmed)) => (
+                format!("{} {}", confirmed.category, confirmed.name),
+                CONFIRMED,
+            ),
+            (None, None) => (String::from("Using controller"), IDLE),
+        };
+        display.fill(&BUTTON, color);
+        Self::draw_label(display, &label, BUTTON.start.x + 6, BUTTON.start.y + 10);
+    }
+}
+
+/// Runs the route confirmed on the touchscreen, falling back to `controller`
+/// when none is.
+pub struct Select<S> {
+    controller: S,
+    touch: TouchChoice,
+}
+
+impl<S> Select<S> {
+    pub fn new(controller: S, touch: TouchChoice) -> Self {
+        Self { controller, touch }
+    }
+}
+
+impl<S: Selector<Robot>> Selector<Robot> for Select<S> {
+    async fn run(&self, robot: &mut Robot) {
+        match self.touch.get() {
+            Some(route) => {
+                info!("running {} {}", route.category, route.name);
+                (route.run)(robot).await;
+            }
+            None => self.controller.run(robot).await,
+        }
+    }
+}
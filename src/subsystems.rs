@@ -1,11 +1,39 @@
 use core::time::Duration;
 
+use pneumatic::PneumaticTiming;
+
+pub mod air;
+pub mod clamp;
+pub mod coordinator;
+pub mod doinker;
 pub mod drivetrain_actions;
+pub mod inertial;
 pub mod intake;
 pub mod lady_brown;
+pub mod motor_monitor;
+pub mod pneumatic;
+
+pub use clamp::Clamp;
+pub use doinker::Doinker;
+pub type IntakeRaiser = pneumatic::TimedPneumatic;
+
+/// Air used (in mL) by each actuation of the pneumatic subsystems, see [`air`].
+pub const CLAMP_AIR_VOLUME: f64 = 8.0;
+pub const DOINKER_AIR_VOLUME: f64 = 2.5;
+pub const INTAKE_RAISER_AIR_VOLUME: f64 = 4.0;
 
-pub type Clamp = libdoxa::subsystems::pneumatic::PneumaticSubsystem<1, false>;
-pub type Doinker = libdoxa::subsystems::pneumatic::MirroredPneumaticSubsystem<1, false>;
-pub type IntakeRaiser = libdoxa::subsystems::pneumatic::PneumaticSubsystem<1, false>;
+/// Measured actuation times of the pneumatic subsystems, see [`pneumatic`].
+pub const CLAMP_TIMING: PneumaticTiming = PneumaticTiming {
+    extend: Duration::from_millis(150),
+    retract: Duration::from_millis(120),
+};
+pub const DOINKER_TIMING: PneumaticTiming = PneumaticTiming {
+    extend: Duration::from_millis(200),
+    retract: Duration::from_millis(150),
+};
+pub const INTAKE_RAISER_TIMING: PneumaticTiming = PneumaticTiming {
+    extend: Duration::from_millis(250),
+    retract: Duration::from_millis(200),
+};
 
 const SUBSYSTEM_UPDATE_PERIOD: Duration = Duration::from_millis(20);
@@ -0,0 +1,145 @@
+use alloc::{boxed::Box, rc::Rc, vec::Vec};
+use core::cell::RefCell;
+
+use log::{info, warn};
+use vexide::{
+    prelude::{sleep, spawn},
+    task::Task,
+};
+
+/// Atmospheric pressure in psi, for converting gauge pressure to absolute.
+const ATMOSPHERIC_PRESSURE: f64 = 14.7;
+
+/// Air system parameters. Pressures are gauge pressures in psi and volumes are
+/// in mL.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AirConfig {
+    /// Total volume of all reservoirs.
+    pub tank_volume: f64,
+    /// Pressure the tanks are pumped to before a match.
+    pub initial_pressure: f64,
+    /// Below this pressure the cylinders no longer actuate reliably.
+    pub min_pressure: f64,
+}
+
+pub const AIR_CONFIG: AirConfig = AirConfig {
+    tank_volume: 400.0,
+    initial_pressure: 100.0,
+    min_pressure: 40.0,
+};
+
+/// A pneumatic subsystem drawing from the tanks.
+pub struct AirLoad {
+    pub name: &'static str,
+    /// Volume of air (in mL) used by each change of state.
+    pub volume: f64,
+    /// Reads whether the subsystem is currently extended.
+    pub extended: Box<dyn Fn() -> bool>,
+}
+
+impl AirLoad {
+    pub fn new(name: &'static str, volume: f64, extended: impl Fn() -> bool + 'static) -> Self {
+        Self {
+            name,
+            volume,
+            extended: Box::new(extended),
+        }
+    }
+}
+
+struct AirBudgetInner {
+    config: AirConfig,
+    pressure: f64,
+    actuations: Vec<(&'static str, u32)>,
+}
+
+impl AirBudgetInner {
+    /// Returns the pressure left after filling `volume` mL of cylinder from
+    /// the tanks at `pressure`, assuming the cylinder started at atmospheric
+    /// pressure.
+    fn pressure_after(&self, pressure: f64, volume: f64) -> f64 {
+        let tank = self.config.tank_volume;
+        ((pressure + ATMOSPHERIC_PRESSURE) * tank + ATMOSPHERIC_PRESSURE * volume) / (tank + volume)
+            - ATMOSPHERIC_PRESSURE
+    }
+}
+
+/// Estimates how much air is left by counting actuations of each pneumatic
+/// subsystem.
+///
+/// The estimate starts from [`AirConfig::initial_pressure`] every time the
+/// program starts, so the tanks should be pumped before each match.
+#[derive(Clone)]
+pub struct AirBudget {
+    inner: Rc<RefCell<AirBudgetInner>>,
+    _task: Rc<Task<()>>,
+}
+
+impl AirBudget {
+    pub fn new(config: AirConfig, loads: Vec<AirLoad>) -> Self {
+        let inner = Rc::new(RefCell::new(AirBudgetInner {
+            config,
+            pressure: config.initial_pressure,
+            actuations: loads.iter().map(|load| (load.name, 0)).collect(),
+        }));
+        let task = spawn({
+            let inner = inner.clone();
+            async move {
+                let mut extended: Vec<bool> = loads.iter().map(|load| (load.extended)()).collect();
+                loop {
+                    for (i, load) in loads.iter().enumerate() {
+                        let now_extended = (load.extended)();
+                        if now_extended != extended[i] {
+                            extended[i] = now_extended;
+                            let mut inner = inner.borrow_mut();
+                            let was_above = inner.pressure >= inner.config.min_pressure;
+                            inner.pressure = inner.pressure_after(inner.pressure, load.volume);
+                            inner.actuations[i].1 += 1;
+                            // The estimate only drops, so this warns once
+                            if was_above && inner.pressure < inner.config.min_pressure {
+                                warn!(
+                                    "air pressure estimate is {:.0} psi, pneumatics may not actuate",
+                                    inner.pressure
+                                );
+                            }
+                        }
+                    }
+                    sleep(super::SUBSYSTEM_UPDATE_PERIOD).await;
+                }
+            }
+        });
+        Self {
+            inner,
+            _task: Rc::new(task),
+        }
+    }
+
+    /// Returns the estimated tank pressure in psi.
+    pub fn pressure(&self) -> f64 {
+        self.inner.borrow().pressure
+    }
+
+    /// Returns how many more changes of state a cylinder using `volume` mL can
+    /// make before the pressure drops below [`AirConfig::min_pressure`].
+    pub fn remaining_actuations(&self, volume: f64) -> u32 {
+        let inner = self.inner.borrow();
+        let mut pressure = inner.pressure;
+        let mut count = 0;
+        loop {
+            pressure = inner.pressure_after(pressure, volume);
+            if pressure < inner.config.min_pressure || count >= 999 {
+                return count;
+            }
+            count += 1;
+        }
+    }
+
+    /// Logs the number of actuations of each subsystem and the estimated pressure.
+    pub fn log_summary(&self) {
+        let inner = self.inner.borrow();
+        for (name, count) in &inner.actuations {
+            info!("{}: {} actuations", name, count);
+        }
+        info!("estimated air pressure: {:.0} psi", inner.pressure);
+    }
+}
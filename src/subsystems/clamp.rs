@@ -0,0 +1,160 @@
+use alloc::rc::Rc;
+use core::{cell::RefCell, time::Duration};
+
+use log::{info, warn};
+use vexide::{
+    prelude::{sleep, spawn, AdiDigitalIn, DistanceSensor},
+    task::Task,
+    time::Instant,
+};
+
+use super::pneumatic::{PneumaticState, TimedPneumatic};
+
+/// A mobile goal closer than this (in mm) to the distance sensor is in the clamp.
+const GOAL_DISTANCE: u32 = 40;
+/// The sensor has to see the goal for this long before it counts.
+const GOAL_DEBOUNCE: Duration = Duration::from_millis(60);
+/// How long [`Clamp::grab`] waits for the sensor before clamping anyway.
+const GRAB_TIMEOUT: Duration = Duration::from_millis(300);
+
+/// Sensor that detects a mobile goal inside the clamp.
+pub enum GoalSensor {
+    Distance(DistanceSensor),
+    Limit(AdiDigitalIn),
+}
+
+impl GoalSensor {
+    fn detects_goal(&self) -> bool {
+        match self {
+            GoalSensor::Distance(sensor) => match sensor.object() {
+                Ok(Some(object)) => object.distance < GOAL_DISTANCE,
+                Ok(None) => false,
+                Err(err) => {
+                    warn!("failed to read clamp distance sensor: {}", err);
+                    false
+                }
+            },
+            GoalSensor::Limit(limit) => limit.is_high().unwrap_or(false),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ClampInner {
+    auto: bool,
+    /// Whether the next detected goal should be grabbed. Disarmed after each
+    /// grab until the goal leaves the clamp, so releasing a goal doesn't
+    /// immediately re-grab it.
+    armed: bool,
+    detected_since: Option<Instant>,
+    goal_detected: bool,
+}
+
+/// Mobile goal clamp that can close itself when a goal is detected.
+#[derive(Clone)]
+pub struct Clamp {
+    pneumatic: TimedPneumatic,
+    inner: Rc<RefCell<ClampInner>>,
+    _task: Option<Rc<Task<()>>>,
+}
+
+impl Clamp {
+    pub fn new(pneumatic: TimedPneumatic, sensor: Option<GoalSensor>) -> Self {
+        let inner = Rc::new(RefCell::new(ClampInner {
+            auto: false,
+            armed: true,
+            detected_since: None,
+            goal_detected: false,
+        }));
+        let task = sensor.map(|sensor| {
+            let inner = inner.clone();
+            let mut pneumatic = pneumatic.clone();
+            Rc::new(spawn(async move {
+                loop {
+                    Clamp::update(&sensor, &mut pneumatic, &mut inner.borrow_mut());
+                    sleep(super::SUBSYSTEM_UPDATE_PERIOD).await;
+                }
+            }))
+        });
+        Self {
+            pneumatic,
+            inner,
+            _task: task,
+        }
+    }
+
+    fn update(sensor: &GoalSensor, pneumatic: &mut TimedPneumatic, inner: &mut ClampInner) {
+        if sensor.detects_goal() {
+            let since = *inner.detected_since.get_or_insert_with(Instant::now);
+            inner.goal_detected = since.elapsed() >= GOAL_DEBOUNCE;
+        } else {
+            inner.detected_since = None;
+            inner.goal_detected = false;
+            inner.armed = true;
+        }
+        if inner.goal_detected && inner.auto && inner.armed {
+            inner.armed = false;
+            if !pneumatic.extended() {
+                info!("goal detected, clamping");
+                pneumatic.extend();
+            }
+        }
+    }
+
+    /// Enables or disables closing the clamp automatically when a goal is detected.
+    pub fn set_auto(&self, auto: bool) {
+        self.inner.borrow_mut().auto = auto;
+    }
+
+    /// Returns `true` if the sensor currently sees a goal in the clamp.
+    pub fn goal_detected(&self) -> bool {
+        self.inner.borrow().goal_detected
+    }
+
+    /// Waits for the sensor to detect a goal and clamps it, clamping anyway if
+    /// nothing is detected within [`GRAB_TIMEOUT`] or there is no sensor.
+    /// Returns once the clamp is fully closed, with whether the goal was
+    /// detected.
+    pub async fn grab(&mut self) -> bool {
+        let start = Instant::now();
+        while self._task.is_some() && !self.goal_detected() {
+            if start.elapsed() > GRAB_TIMEOUT {
+                warn!("no goal detected, clamping anyway");
+                break;
+            }
+            sleep(super::SUBSYSTEM_UPDATE_PERIOD).await;
+        }
+        self.pneumatic.extend_and_wait().await;
+        self.goal_detected()
+    }
+
+    /// Closes the clamp where a route expects the goal to be in it, unless a
+    /// goal sensor is fitted. With a sensor, auto mode closes the clamp as
+    /// soon as the goal is actually in it, and [`Self::grab`] makes sure it
+    /// closes once the route gets there.
+    pub fn grab_without_sensor(&mut self) {
+        if self._task.is_none() && !self.pneumatic.extended() {
+            self.pneumatic.extend();
+        }
+    }
+
+    pub fn extend(&mut self) {
+        self.pneumatic.extend();
+    }
+
+    pub fn retract(&mut self) {
+        self.pneumatic.retract();
+    }
+
+    pub fn toggle(&mut self) {
+        self.pneumatic.toggle();
+    }
+
+    pub fn extended(&self) -> bool {
+        self.pneumatic.extended()
+    }
+
+    pub fn state(&self) -> PneumaticState {
+        self.pneumatic.state()
+    }
+}
@@ -0,0 +1,73 @@
+use alloc::rc::Rc;
+use core::ops::Range;
+
+use vexide::{
+    float::Float as _,
+    prelude::{sleep, spawn},
+    task::Task,
+};
+
+use super::{
+    intake::{Intake, IntakeCommand, IntakePriority},
+    lady_brown::{LadyBrown, LadyBrownState},
+};
+
+/// Arm angles (in degrees) where the arm passes over the top of the intake.
+const SWING_ZONE: Range<f64> =
+    (LadyBrownState::INTAKE_ANGLE + 10.0)..(LadyBrownState::INITIAL_ARM_ANGLE + 100.0);
+/// The arm is considered parked, not swinging, within this many degrees of its target.
+const SWING_TOLERANCE: f64 = 5.0;
+
+/// Enforces interlocks between the lady brown and the intake so routes and
+/// drivers don't have to remember them:
+///
+/// - The intake never drives while the arm swings over it.
+/// - While the arm is in the intake position, a stopped intake holds the belt
+///   so the loaded ring stays on the arm.
+#[derive(Debug, Clone)]
+pub struct Coordinator {
+    _task: Rc<Task<()>>,
+}
+
+impl Coordinator {
+    pub fn new(intake: Intake, lady_brown: LadyBrown) -> Self {
+        let task = spawn(async move {
+            let mut swing_locked = false;
+            loop {
+                Coordinator::update(&intake, &lady_brown, &mut swing_locked);
+                sleep(super::SUBSYSTEM_UPDATE_PERIOD).await;
+            }
+        });
+        Self {
+            _task: Rc::new(task),
+        }
+    }
+
+    fn update(intake: &Intake, lady_brown: &LadyBrown, swing_locked: &mut bool) {
+        let angle = lady_brown.angle();
+        let state = lady_brown.state();
+
+        let swinging =
+            SWING_ZONE.contains(&angle) && (state.angle() - angle).abs() > SWING_TOLERANCE;
+        if swinging != *swing_locked {
+            if swinging {
+                log::debug!("lady brown swinging, locking intake");
+                intake.request(IntakePriority::Interlock, IntakeCommand::Stop);
+            } else {
+                intake.release(IntakePriority::Interlock);
+            }
+            *swing_locked = swinging;
+        }
+
+        let loading = matches!(state, LadyBrownState::Intake);
+        match intake.command(IntakePriority::Default) {
+            Some(IntakeCommand::Stop) if loading => {
+                intake.request(IntakePriority::Default, IntakeCommand::StopHold);
+            }
+            Some(IntakeCommand::StopHold) if !loading => {
+                intake.request(IntakePriority::Default, IntakeCommand::Stop);
+            }
+            _ => {}
+        }
+    }
+}
@@ -1,40 +1,210 @@
-use snafu::ResultExt;
+use alloc::rc::Rc;
+use core::{
+    cell::{Cell, RefCell},
+    time::Duration,
+};
 
+use libdoxa::subsystems::drivetrain::{
+    drivetrain_pair::DrivetrainUnits, Drivetrain, DrivetrainPair,
+};
+use log::{info, warn};
+use vexide::{
+    prelude::{sleep, InertialSensor},
+    time::Instant,
+};
+
+use super::{intake::RingColor, pneumatic::TimedPneumatic};
+use crate::utils::battery;
+
+/// A timed sweep: extend an arm, turn in place towards it, then retract.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sweep {
+    /// How far to turn, in degrees.
+    pub angle: f64,
+    /// Drive voltage used for the turn.
+    pub voltage: f64,
+    /// Give up on the turn after this long, e.g. if the arm is caught.
+    pub timeout: Duration,
+}
+
+impl Sweep {
+    /// Sweeps rings out of a corner with the dominant arm.
+    pub const CORNER: Sweep = Sweep {
+        angle: 120.0,
+        voltage: 6.0,
+        timeout: Duration::from_millis(1000),
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DoinkerArm {
+    Dominant,
+    NonDominant,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Left,
+    Right,
+}
+
+/// The pair of doinker arms on the front of the robot.
+///
+/// Which arm is dominant depends on the alliance, since blue routes are
+/// mirrored versions of red ones: on red the right arm is dominant and on
+/// blue the left arm is.
+#[derive(Clone)]
 pub struct Doinker {
-    adi_out: vexide::devices::adi::AdiDigitalOut,
+    pub left: TimedPneumatic,
+    pub right: TimedPneumatic,
+    alliance: Rc<Cell<RingColor>>,
+    inertial: Rc<RefCell<InertialSensor>>,
+    /// Turn voltage wanted by a running sweep, positive clockwise.
+    sweep_voltage: Rc<Cell<Option<f64>>>,
 }
 
-#[derive(Debug, snafu::Snafu)]
-pub enum DoinkerError {
-    #[snafu(display("digital output port error: {}", source))]
-    DigitalPort { source: vexide::devices::PortError },
+/// Stops the turn and retracts the arms if a sweep is cancelled.
+struct SweepGuard(Doinker);
+
+impl Drop for SweepGuard {
+    fn drop(&mut self) {
+        self.0.sweep_voltage.set(None);
+        self.0.left.retract();
+        self.0.right.retract();
+    }
 }
 
 impl Doinker {
-    pub fn new(adi_out: vexide::devices::adi::AdiDigitalOut) -> Self {
-        Self { adi_out }
+    pub fn new(
+        left: TimedPneumatic,
+        right: TimedPneumatic,
+        inertial: Rc<RefCell<InertialSensor>>,
+    ) -> Self {
+        Self {
+            left,
+            right,
+            alliance: Rc::new(Cell::new(RingColor::Red)),
+            inertial,
+            sweep_voltage: Rc::new(Cell::new(None)),
+        }
     }
 
-    pub fn toggle(&mut self) -> Result<(), DoinkerError> {
-        match self.adi_out.level().context(DigitalPortSnafu)? {
-            vexide::devices::adi::digital::LogicLevel::High => self.undoink(),
-            vexide::devices::adi::digital::LogicLevel::Low => self.doink(),
+    pub fn set_alliance(&self, alliance: RingColor) {
+        self.alliance.set(alliance);
+    }
+
+    fn side(&self, arm: DoinkerArm) -> Side {
+        match (self.alliance.get(), arm) {
+            (RingColor::Red, DoinkerArm::Dominant) | (RingColor::Blue, DoinkerArm::NonDominant) => {
+                Side::Right
+            }
+            (RingColor::Red, DoinkerArm::NonDominant) | (RingColor::Blue, DoinkerArm::Dominant) => {
+                Side::Left
+            }
         }
     }
 
-    /// Don't ask about the name of the function. That was GitHub Copilot's idea.
-    pub fn doink(&mut self) -> Result<(), DoinkerError> {
-        self.adi_out
-            .set_level(vexide::devices::adi::digital::LogicLevel::High)
-            .context(DigitalPortSnafu)?;
-        Ok(())
+    pub fn arm(&mut self, arm: DoinkerArm) -> &mut TimedPneumatic {
+        match self.side(arm) {
+            Side::Left => &mut self.left,
+            Side::Right => &mut self.right,
+        }
+    }
+
+    pub fn dominant(&mut self) -> &mut TimedPneumatic {
+        self.arm(DoinkerArm::Dominant)
+    }
+
+    pub fn non_dominant(&mut self) -> &mut TimedPneumatic {
+        self.arm(DoinkerArm::NonDominant)
+    }
+
+    /// Returns the turn voltage (positive clockwise) a running sweep wants the
+    /// drivetrain driven at, in place of other drive input.
+    pub fn sweep_voltage(&self) -> Option<f64> {
+        self.sweep_voltage.get()
+    }
+
+    /// Runs `sweep` with `arm`, using the inertial sensor to measure the turn.
+    ///
+    /// The drivetrain is driven directly, so this must not run at the same time
+    /// as a drivetrain action.
+    pub async fn sweep(&mut self, drivetrain: &mut Drivetrain, arm: DoinkerArm, sweep: Sweep) {
+        self.run_sweep(arm, sweep, |voltage| {
+            let voltage = voltage * battery::compensation();
+            drivetrain.set_voltage(DrivetrainPair {
+                left: voltage,
+                right: -voltage,
+                units: DrivetrainUnits::Voltage,
+            });
+        })
+        .await;
+    }
+
+    /// Runs `sweep` with `arm` like [`Doinker::sweep`], without driving the
+    /// drivetrain so the sweep can run in its own task: whoever drives it
+    /// applies [`Doinker::sweep_voltage`] instead. Dropping the sweep stops the
+    /// turn and retracts the arms.
+    pub async fn sweep_in_task(&mut self, arm: DoinkerArm, sweep: Sweep) {
+        let sweep_voltage = self.sweep_voltage.clone();
+        self.run_sweep(arm, sweep, |voltage| {
+            sweep_voltage.set((voltage != 0.0).then_some(voltage));
+        })
+        .await;
+    }
+
+    /// Extends `arm`, turns towards it with `drive` until the sweep is done and
+    /// retracts it. `drive` gets the turn voltage, positive clockwise, and zero
+    /// once the turn is over.
+    async fn run_sweep(&mut self, arm: DoinkerArm, sweep: Sweep, mut drive: impl FnMut(f64)) {
+        let side = self.side(arm);
+        // Positive rotation is clockwise, so the right arm sweeps clockwise
+        let direction = match side {
+            Side::Left => -1.0,
+            Side::Right => 1.0,
+        };
+        let _guard = SweepGuard(self.clone());
+        self.left.retract();
+        self.right.retract();
+        self.arm(arm).extend_and_wait().await;
+
+        let start_rotation = self.inertial.borrow().rotation().ok();
+        let start = Instant::now();
+        loop {
+            let turned = match (start_rotation, self.inertial.borrow().rotation().ok()) {
+                (Some(initial), Some(rotation)) => Some((rotation - initial) * direction),
+                _ => None,
+            };
+            match turned {
+                Some(turned) if turned >= sweep.angle => break,
+                // Without a heading the sweep can only be timed
+                None if start.elapsed() >= sweep.timeout => break,
+                _ if start.elapsed() >= sweep.timeout => {
+                    warn!("doinker sweep timed out");
+                    break;
+                }
+                _ => {}
+            }
+            drive(sweep.voltage * direction);
+            sleep(super::SUBSYSTEM_UPDATE_PERIOD).await;
+        }
+        drive(0.0);
+
+        self.arm(arm).retract_and_wait().await;
+    }
+
+    /// Clears rings out of a corner with the dominant arm.
+    pub async fn clear_corner(&mut self, drivetrain: &mut Drivetrain) {
+        info!("clearing corner");
+        self.sweep(drivetrain, DoinkerArm::Dominant, Sweep::CORNER)
+            .await;
     }
 
-    /// Don't ask about the name of the function. That was GitHub Copilot's idea.
-    pub fn undoink(&mut self) -> Result<(), DoinkerError> {
-        self.adi_out
-            .set_level(vexide::devices::adi::digital::LogicLevel::Low)
-            .context(DigitalPortSnafu)?;
-        Ok(())
+    /// Clears rings out of a corner like [`Doinker::clear_corner`], for a
+    /// sweep running in its own task. See [`Doinker::sweep_in_task`].
+    pub async fn clear_corner_in_task(&mut self) {
+        info!("clearing corner");
+        self.sweep_in_task(DoinkerArm::Dominant, Sweep::CORNER)
+            .await;
     }
 }
@@ -5,6 +5,8 @@ use libdoxa::{
     subsystems::drivetrain::actions::config::ActionConfig, utils::pose::Pose,
 };
 
+use crate::utils::battery;
+
 pub const TILES_TO_MM: f64 = 600.0;
 
 pub const CONFIG: ActionConfig = ActionConfig {
@@ -41,13 +43,36 @@ pub const CONFIG: ActionConfig = ActionConfig {
     turn_timeout: Duration::from_millis(1000),
 };
 
+/// Scales the gains and output limits of `config` by the battery
+/// compensation, so routes drive the same as the battery sags.
+///
+/// The battery is read when the action is built, right before it runs.
+fn compensated(config: ActionConfig) -> ActionConfig {
+    let factor = battery::compensation();
+    ActionConfig {
+        linear_kp: config.linear_kp * factor,
+        linear_ki: config.linear_ki * factor,
+        linear_kd: config.linear_kd * factor,
+        linear_limit: config.linear_limit * factor,
+        turn_kp: config.turn_kp * factor,
+        turn_ki: config.turn_ki * factor,
+        turn_kd: config.turn_kd * factor,
+        turn_limit: config.turn_limit * factor,
+        pursuit_turn_kp: config.pursuit_turn_kp * factor,
+        pursuit_turn_ki: config.pursuit_turn_ki * factor,
+        pursuit_turn_kd: config.pursuit_turn_kd * factor,
+        pursuit_turn_limit: config.pursuit_turn_limit * factor,
+        ..config
+    }
+}
+
 pub fn forward(
     distance_tiles: f64,
     config: ActionConfig,
 ) -> impl libdoxa::subsystems::drivetrain::actions::Action {
     libdoxa::subsystems::drivetrain::actions::ForwardAction::new(
         distance_tiles * TILES_TO_MM,
-        config,
+        compensated(config),
     )
 }
 
@@ -58,7 +83,7 @@ pub fn turn_to_point(
     libdoxa::subsystems::drivetrain::actions::TurnToPointAction::new(
         point * TILES_TO_MM,
         false,
-        config,
+        compensated(config),
     )
 }
 
@@ -70,7 +95,7 @@ pub fn drive_to_point(
     libdoxa::subsystems::drivetrain::actions::DriveToPointAction::new(
         point * TILES_TO_MM,
         reverse,
-        config,
+        compensated(config),
     )
 }
 
@@ -78,7 +103,10 @@ pub fn boomerang_to_point(
     point: Pose,
     config: ActionConfig,
 ) -> impl libdoxa::subsystems::drivetrain::actions::Action {
-    libdoxa::subsystems::drivetrain::actions::BoomerangAction::new(point * TILES_TO_MM, config)
+    libdoxa::subsystems::drivetrain::actions::BoomerangAction::new(
+        point * TILES_TO_MM,
+        compensated(config),
+    )
 }
 
 #[allow(unused)]
@@ -106,7 +134,7 @@ pub fn smooth_to_point(
                 end_easing * TILES_TO_MM,
             ),
             disable_seeking_distance,
-            config,
+            compensated(config),
         )
     })
 }
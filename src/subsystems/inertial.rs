@@ -0,0 +1,179 @@
+use alloc::rc::Rc;
+use core::{cell::RefCell, fmt, time::Duration};
+
+use log::{error, info, warn};
+use vexide::{
+    competition::{self, CompetitionMode},
+    devices::smart::{SmartDevice, SmartPort},
+    prelude::{sleep, spawn, InertialSensor},
+    task::Task,
+    time::Instant,
+};
+
+/// How often the heading is sampled to look for drift.
+const SAMPLE_PERIOD: Duration = Duration::from_secs(1);
+/// Heading changes faster than this (in °/s) mean the robot is being moved,
+/// not that the sensor drifts.
+const HANDLING_RATE: f64 = 2.0;
+/// How long the heading has to change steadily before it counts as drift.
+const DRIFT_WINDOW: Duration = Duration::from_secs(10);
+/// Steady heading changes faster than this (in °/s) while disabled count as
+/// drift.
+const DRIFT_RATE: f64 = 0.05;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InertialState {
+    Calibrating,
+    Ready,
+    /// Calibration timed out or the sensor couldn't be read.
+    Failed,
+    /// The heading kept changing while the robot was disabled and still.
+    Drifting,
+}
+
+impl fmt::Display for InertialState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InertialState::Calibrating => f.write_str("calibrating"),
+            InertialState::Ready => f.write_str("ready"),
+            InertialState::Failed => f.write_str("failed"),
+            InertialState::Drifting => f.write_str("drifting"),
+        }
+    }
+}
+
+struct InertialCalibrationInner {
+    state: InertialState,
+    recalibrate: bool,
+}
+
+/// Calibrates the inertial sensor used for tracking when the program starts,
+/// and watches it for drift while the robot is disabled.
+///
+/// Calibrating again can be requested with [`InertialCalibration::recalibrate`],
+/// which only happens while the robot is disabled since the heading is lost.
+#[derive(Clone)]
+pub struct InertialCalibration {
+    inner: Rc<RefCell<InertialCalibrationInner>>,
+    _task: Rc<Task<()>>,
+}
+
+impl InertialCalibration {
+    pub fn new(inertial: Rc<RefCell<InertialSensor>>) -> Self {
+        let inner = Rc::new(RefCell::new(InertialCalibrationInner {
+            state: InertialState::Calibrating,
+            recalibrate: false,
+        }));
+        // Calibration gets its own handle on the port so it can be awaited
+        // without borrowing the shared sensor, which placement and tracking
+        // keep reading in the meantime.
+        // SAFETY: the handle is only used to calibrate, which doesn't touch
+        // the shared sensor's offsets
+        let mut calibration =
+            InertialSensor::new(unsafe { SmartPort::new(inertial.borrow().port_number()) });
+        let task = spawn({
+            let inner = inner.clone();
+            async move {
+                loop {
+                    inner.borrow_mut().state = InertialState::Calibrating;
+                    if calibration.is_calibrating().unwrap_or(false) {
+                        info!("inertial sensor is already calibrating");
+                    }
+                    let start = Instant::now();
+                    let state = match calibration.calibrate().await {
+                        Ok(()) => {
+                            info!("inertial sensor calibrated in {:?}", start.elapsed());
+                            InertialState::Ready
+                        }
+                        Err(err) => {
+                            error!("failed to calibrate inertial sensor: {}", err);
+                            InertialState::Failed
+                        }
+                    };
+                    inner.borrow_mut().state = state;
+                    Self::watch(&inertial, &inner).await;
+                }
+            }
+        });
+        Self {
+            inner,
+            _task: Rc::new(task),
+        }
+    }
+
+    /// Watches for drift until recalibration is requested.
+    async fn watch(inertial: &RefCell<InertialSensor>, inner: &RefCell<InertialCalibrationInner>) {
+        let mut last: Option<f64> = None;
+        let mut window: Option<(Instant, f64)> = None;
+        loop {
+            sleep(SAMPLE_PERIOD).await;
+            {
+                let mut inner = inner.borrow_mut();
+                if inner.recalibrate {
+                    inner.recalibrate = false;
+                    return;
+                }
+                if inner.state == InertialState::Failed {
+                    continue;
+                }
+            }
+            // The robot only stays still while disabled
+            let rotation = match inertial.borrow().rotation() {
+                Ok(rotation) if competition::mode() == CompetitionMode::Disabled => rotation,
+                _ => {
+                    last = None;
+                    window = None;
+                    continue;
+                }
+            };
+            let handled = last.is_some_and(|last| {
+                (rotation - last).abs() > HANDLING_RATE * SAMPLE_PERIOD.as_secs_f64()
+            });
+            last = Some(rotation);
+            let Some((start, start_rotation)) = window.filter(|_| !handled) else {
+                window = Some((Instant::now(), rotation));
+                continue;
+            };
+            if start.elapsed() < DRIFT_WINDOW {
+                continue;
+            }
+            window = Some((Instant::now(), rotation));
+
+            let rate = (rotation - start_rotation).abs() / start.elapsed().as_secs_f64();
+            let mut inner = inner.borrow_mut();
+            if rate > DRIFT_RATE {
+                if inner.state != InertialState::Drifting {
+                    warn!("inertial sensor is drifting ({:.2} deg/s)", rate);
+                }
+                inner.state = InertialState::Drifting;
+            } else if inner.state == InertialState::Drifting {
+                info!("inertial sensor stopped drifting");
+                inner.state = InertialState::Ready;
+            }
+        }
+    }
+
+    pub fn state(&self) -> InertialState {
+        self.inner.borrow().state
+    }
+
+    /// Calibrates the sensor again if the robot is disabled.
+    pub fn recalibrate(&self) {
+        if competition::mode() != CompetitionMode::Disabled {
+            warn!("not recalibrating the inertial sensor while enabled");
+            return;
+        }
+        info!("recalibrating inertial sensor");
+        let mut inner = self.inner.borrow_mut();
+        inner.recalibrate = true;
+        inner.state = InertialState::Calibrating;
+    }
+
+    /// Waits for calibration to finish. Returns whether it succeeded.
+    pub async fn wait_calibrated(&self) -> bool {
+        while self.state() == InertialState::Calibrating {
+            sleep(super::SUBSYSTEM_UPDATE_PERIOD).await;
+        }
+        self.state() != InertialState::Failed
+    }
+}
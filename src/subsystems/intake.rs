@@ -2,10 +2,10 @@ use alloc::rc::Rc;
 use core::{cell::RefCell, ops::Not, time::Duration};
 
 use colorsys::{ColorAlpha, Hsl, Rgb};
-use log::error;
+use log::{error, info, warn};
 use snafu::{ResultExt, Snafu};
 use vexide::{
-    devices::smart::{motor::MotorError, vision::DetectionSource},
+    devices::smart::{motor::MotorError, vision::DetectionSource, SmartDevice},
     float::Float as _,
     prelude::{
         sleep, spawn, AdiLineTracker, BrakeMode, Direction, Motor, OpticalSensor, VisionMode,
@@ -14,13 +14,36 @@ use vexide::{
     time::Instant,
 };
 
+use super::motor_monitor::MotorSample;
+
 const JAM_CURRENT: f64 = 2.6;
 const JAM_OVERCURRENT_TIME: Duration = Duration::from_millis(1000);
-const JAM_REVERSE_TIME: Duration = Duration::from_millis(500);
-const RING_REJECT_STOP_TIME: Duration = Duration::from_millis(100);
-const RING_REJECT_RESTART_TIME: Duration = Duration::from_millis(300);
+/// Below this velocity (in RPM) the intake is considered stalled.
+const JAM_STALL_VELOCITY: f64 = 40.0;
+const JAM_STALL_TIME: Duration = Duration::from_millis(400);
+/// A jam detected within this window of the previous one escalates the recovery.
+const JAM_ESCALATION_WINDOW: Duration = Duration::from_millis(1500);
+/// Belt travel from the line tracker to the apex of the hook, in mm.
+const RING_REJECT_APEX_DISTANCE: f64 = 76.0;
+/// Belt travel per motor revolution, in mm.
+const BELT_TRAVEL_PER_REV: f64 = 76.2;
+const RING_REJECT_BRAKE_TIME: Duration = Duration::from_millis(200);
+/// Give up on a rejection if the ring never reaches the apex, e.g. because the belt stalled.
+const RING_REJECT_TIMEOUT: Duration = Duration::from_millis(1000);
 const LINE_TRACKER_THRESHOLD: f64 = 0.1;
-const RPM: i32 = 600;
+/// Line tracker readings within this of either end of the range mean nothing
+/// is plugged in, since an ADI port can't report it.
+const LINE_TRACKER_RAIL: f64 = 0.01;
+/// A line tracker whose reading doesn't change for this long while the belt
+/// moves past it is considered broken.
+const LINE_TRACKER_STUCK_TIME: Duration = Duration::from_millis(2000);
+/// How long partial intake runs for when the line tracker can't see the ring.
+const PARTIAL_INTAKE_TIME: Duration = Duration::from_millis(400);
+/// Velocity used to back out of a jam, regardless of the active [`IntakeSpeeds`].
+const JAM_RPM: i32 = 600;
+/// How long the intake stays stopped after jam recovery gives up before
+/// trying again.
+const JAM_GIVE_UP_TIME: Duration = Duration::from_millis(3000);
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[allow(unused)]
@@ -87,25 +110,236 @@ impl Not for RingColor {
     }
 }
 
+/// Progress of a ring being rejected off the top of the intake.
+#[derive(Debug, Clone, Copy)]
+pub enum RejectState {
+    /// The ring is travelling from the line tracker towards the hook apex.
+    Travelling {
+        distance: f64,
+        start: Instant,
+        last_update: Instant,
+    },
+    /// The belt is braked so the ring flies off the hook.
+    Braking { start: Instant },
+}
+
+/// Escalating stages of jam recovery.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JamStage {
+    /// Briefly reverse to free a ring caught on a hook.
+    BackOff,
+    /// Reverse for longer to clear the whole belt.
+    Reverse,
+    /// Stop the intake until [`JAM_GIVE_UP_TIME`] passes or the intake is
+    /// requested again.
+    GiveUp,
+}
+
+impl JamStage {
+    pub const fn duration(&self) -> Duration {
+        match self {
+            JamStage::BackOff => Duration::from_millis(150),
+            JamStage::Reverse => Duration::from_millis(500),
+            JamStage::GiveUp => Duration::ZERO,
+        }
+    }
+
+    pub const fn next(&self) -> Self {
+        match self {
+            JamStage::BackOff => JamStage::Reverse,
+            JamStage::Reverse | JamStage::GiveUp => JamStage::GiveUp,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum IntakeState {
     Forward {
         current_ring: Option<RingColor>,
         red_time: Option<Instant>,
-        reject_time: Option<Instant>,
+        reject: Option<RejectState>,
         jam_time: Option<Instant>,
+        jam_stage: Option<JamStage>,
         overcurrent_time: Option<Instant>,
+        stall_time: Option<Instant>,
+    },
+    PartialIntake {
+        start: Instant,
     },
-    PartialIntake,
     Reverse,
     Stop,
     StopHold,
 }
 
+/// Intake features that depend on sensors, which are lost if the sensors are
+/// missing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IntakeCapabilities {
+    /// Rejecting rings of the wrong color. Needs the vision or optical sensor
+    /// to see the color and the line tracker to time the rejection.
+    pub color_sorting: bool,
+    /// Stopping partial intake once a ring reaches the line tracker. Without
+    /// it, partial intake runs for a fixed time instead.
+    pub ring_detection: bool,
+}
+
+impl IntakeCapabilities {
+    const ALL: IntakeCapabilities = IntakeCapabilities {
+        color_sorting: true,
+        ring_detection: true,
+    };
+}
+
+/// Something that wants to control the intake. Higher priorities win; when a
+/// request is released, control falls back to the next highest request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum IntakePriority {
+    /// Routes and the resting state between driver inputs.
+    Default,
+    /// Multi-step opcontrol macros.
+    Macro,
+    /// Buttons held by the driver.
+    Driver,
+    /// Jam recovery.
+    Safety,
+    /// Interlocks with other subsystems, see [`super::coordinator`].
+    Interlock,
+}
+
+impl IntakePriority {
+    const COUNT: usize = 5;
+}
+
+/// A command for the intake, arbitrated by [`IntakePriority`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntakeCommand {
+    Forward,
+    Reverse,
+    PartialIntake,
+    Stop,
+    StopHold,
+}
+
+impl IntakeCommand {
+    fn state(self) -> IntakeState {
+        match self {
+            IntakeCommand::Forward => IntakeState::Forward {
+                reject: None,
+                jam_time: None,
+                jam_stage: None,
+                overcurrent_time: None,
+                stall_time: None,
+                current_ring: None,
+                red_time: None,
+            },
+            IntakeCommand::Reverse => IntakeState::Reverse,
+            IntakeCommand::PartialIntake => IntakeState::PartialIntake {
+                start: Instant::now(),
+            },
+            IntakeCommand::Stop => IntakeState::Stop,
+            IntakeCommand::StopHold => IntakeState::StopHold,
+        }
+    }
+}
+
+/// Target velocities (in RPM) for each [`IntakeState`] that drives the motor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IntakeSpeeds {
+    pub forward: f64,
+    pub partial_intake: f64,
+    pub reverse: f64,
+    /// Maximum change in commanded velocity, in RPM per second.
+    pub acceleration: f64,
+}
+
+impl IntakeSpeeds {
+    /// Full speed for scoring on a mobile goal.
+    pub const MOBILE_GOAL: Self = Self {
+        forward: 600.0,
+        partial_intake: 600.0,
+        reverse: 600.0,
+        acceleration: 6000.0,
+    };
+    /// Slower feeding so rings seat on the lady brown instead of flying past it.
+    pub const LADY_BROWN: Self = Self {
+        forward: 350.0,
+        partial_intake: 350.0,
+        ..Self::MOBILE_GOAL
+    };
+    /// Gentle reverse for spitting rings out of the intake.
+    pub const EJECT: Self = Self {
+        reverse: 200.0,
+        ..Self::MOBILE_GOAL
+    };
+}
+
+impl Default for IntakeSpeeds {
+    fn default() -> Self {
+        Self::MOBILE_GOAL
+    }
+}
+
+/// Moves `velocity` towards `target` by at most one update period's worth of
+/// acceleration and returns the new velocity to command.
+fn ramp(velocity: &mut f64, target: f64, acceleration: f64) -> i32 {
+    let max_step = acceleration * super::SUBSYSTEM_UPDATE_PERIOD.as_secs_f64();
+    *velocity += (target - *velocity).clamp(-max_step, max_step);
+    *velocity as i32
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct IntakeInner {
     accept: Option<RingColor>,
     state: IntakeState,
+    jam_count: u32,
+    jam_alert: bool,
+    speeds: IntakeSpeeds,
+    velocity: f64,
+    commands: [Option<IntakeCommand>; IntakePriority::COUNT],
+    /// When jam recovery gave up and stopped the intake.
+    give_up_time: Option<Instant>,
+    capabilities: IntakeCapabilities,
+    motor_sample: Option<MotorSample>,
+}
+
+impl IntakeInner {
+    fn active_priority(&self) -> Option<IntakePriority> {
+        [
+            IntakePriority::Interlock,
+            IntakePriority::Safety,
+            IntakePriority::Driver,
+            IntakePriority::Macro,
+            IntakePriority::Default,
+        ]
+        .into_iter()
+        .find(|priority| self.commands[*priority as usize].is_some())
+    }
+
+    fn request(&mut self, priority: IntakePriority, command: IntakeCommand) {
+        // A new request means someone wants the intake again after a give-up
+        if priority < IntakePriority::Safety && self.give_up_time.is_some() {
+            self.release(IntakePriority::Safety);
+        }
+        self.commands[priority as usize] = Some(command);
+        if self.active_priority() == Some(priority) {
+            self.state = command.state();
+        }
+    }
+
+    fn release(&mut self, priority: IntakePriority) {
+        if priority == IntakePriority::Safety {
+            self.give_up_time = None;
+        }
+        let was_active = self.active_priority() == Some(priority);
+        self.commands[priority as usize] = None;
+        if was_active {
+            self.state = self
+                .active_priority()
+                .and_then(|priority| self.commands[priority as usize])
+                .unwrap_or(IntakeCommand::Stop)
+                .state();
+        }
+    }
 }
 
 #[derive(Debug, Snafu)]
@@ -114,6 +348,94 @@ pub enum IntakeError {
     Motor { source: MotorError },
 }
 
+/// The line tracker under the belt, which detects rings passing the hook.
+///
+/// ADI devices can't report whether they are plugged in, so the sensor counts
+/// as missing while its reading sits at either end of the range, or doesn't
+/// change at all while the belt moves.
+struct LineTracker {
+    sensor: AdiLineTracker,
+    /// Reading without a ring in front of the sensor.
+    zero: Option<f64>,
+    last: Option<f64>,
+    /// How long the belt has moved without the reading changing.
+    stuck_for: Duration,
+}
+
+impl LineTracker {
+    fn new(sensor: AdiLineTracker) -> Self {
+        let mut line_tracker = Self {
+            sensor,
+            zero: None,
+            last: None,
+            stuck_for: Duration::ZERO,
+        };
+        line_tracker.rezero();
+        line_tracker
+    }
+
+    fn reflectivity(&self) -> Option<f64> {
+        self.sensor.reflectivity().ok().filter(|reflectivity| {
+            (LINE_TRACKER_RAIL..=1.0 - LINE_TRACKER_RAIL).contains(reflectivity)
+        })
+    }
+
+    /// Takes the current reading as the reading without a ring.
+    fn rezero(&mut self) {
+        match self.reflectivity() {
+            Some(zero) => self.zero = Some(zero),
+            None => warn!("couldn't zero the line tracker, it isn't plugged in"),
+        }
+    }
+
+    /// Returns whether a ring is in front of the sensor, or `None` if the
+    /// sensor is missing.
+    fn ring_detected(&mut self, belt_moving: bool) -> Option<bool> {
+        let reflectivity = self.reflectivity();
+        if reflectivity != self.last {
+            self.stuck_for = Duration::ZERO;
+        } else if belt_moving {
+            self.stuck_for += super::SUBSYSTEM_UPDATE_PERIOD;
+        }
+        self.last = reflectivity;
+        if self.stuck_for > LINE_TRACKER_STUCK_TIME {
+            return None;
+        }
+        let reflectivity = reflectivity?;
+        // A sensor plugged in after startup is zeroed on its first reading
+        let zero = *self.zero.get_or_insert(reflectivity);
+        Some(reflectivity > zero + LINE_TRACKER_THRESHOLD)
+    }
+}
+
+/// Sets up the vision sensor's detection and signatures. The sensor forgets
+/// them when it is unplugged, so this runs again when it reconnects.
+fn configure_vision(vision: &mut VisionSensor) {
+    if let Err(err) = vision
+        .set_mode(VisionMode::MixedDetection)
+        .and_then(|_| vision.set_brightness(1.2))
+    {
+        warn!("failed to configure vision sensor: {}", err);
+    }
+    for (id, signature) in RingColor::Blue
+        .signatures()
+        .iter()
+        .chain(RingColor::Red.signatures())
+    {
+        if let Err(err) = vision.set_signature(*id, *signature) {
+            warn!("failed to initialize vision signature: {}", err);
+            break;
+        }
+    }
+}
+
+/// Sets the optical sensor's LED brightness, again when it reconnects.
+fn configure_optical(optical: &mut OpticalSensor) {
+    if let Err(err) = optical.set_led_brightness(0.8) {
+        warn!("could not set optical sensor brightness: {}", err);
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Intake {
     state: Rc<RefCell<IntakeInner>>,
@@ -124,36 +446,30 @@ impl Intake {
     pub fn new(
         mut motor: Motor,
         mut vision: VisionSensor,
-        mut line_tracker: AdiLineTracker,
+        line_tracker: AdiLineTracker,
         mut optical: OpticalSensor,
     ) -> Self {
-        optical
-            .set_led_brightness(0.8)
-            .expect("could not set optical sensor brightness");
-        vision
-            .set_mode(VisionMode::MixedDetection)
-            .expect("failed to set vision mode");
-        vision
-            .set_brightness(1.2)
-            .expect("failed to set vision brightness");
-        for (id, signature) in RingColor::Blue
-            .signatures()
-            .iter()
-            .chain(RingColor::Red.signatures())
-        {
-            vision
-                .set_signature(*id, *signature)
-                .expect("failed to initialize vision signature");
-        }
-        let mut line_tracker_zero = line_tracker
-            .reflectivity()
-            .expect("couldn't zero the line tracker");
+        // Failures here are reported by the self test, so keep going without
+        // the sensor instead of panicking
+        configure_optical(&mut optical);
+        configure_vision(&mut vision);
+        let mut vision_connected = vision.is_connected();
+        let mut optical_connected = optical.is_connected();
+        let mut line_tracker = LineTracker::new(line_tracker);
 
         let mut sleeper = Some(Instant::now());
 
         let state = Rc::new(RefCell::new(IntakeInner {
             accept: None,
             state: IntakeState::Stop,
+            jam_count: 0,
+            jam_alert: false,
+            speeds: IntakeSpeeds::default(),
+            velocity: 0.0,
+            commands: [None; IntakePriority::COUNT],
+            give_up_time: None,
+            capabilities: IntakeCapabilities::ALL,
+            motor_sample: None,
         }));
         Self {
             state: state.clone(),
@@ -164,23 +480,40 @@ impl Intake {
                         if let Some(time) = sleeper
                             && time.elapsed() > Duration::from_millis(100)
                         {
-                            line_tracker_zero = line_tracker
-                                .reflectivity()
-                                .expect("couldn't zero the line tracker");
+                            line_tracker.rezero();
                             sleeper = None;
                         }
+                        if vision.is_connected() != vision_connected {
+                            vision_connected = !vision_connected;
+                            if vision_connected {
+                                info!("vision sensor reconnected, configuring it again");
+                                configure_vision(&mut vision);
+                            }
+                        }
+                        if optical.is_connected() != optical_connected {
+                            optical_connected = !optical_connected;
+                            if optical_connected {
+                                configure_optical(&mut optical);
+                            }
+                        }
                         {
                             let mut state = state.borrow_mut();
                             if let Err(err) = Intake::update(
                                 &mut motor,
                                 &mut vision,
                                 &mut line_tracker,
-                                line_tracker_zero,
                                 &mut optical,
                                 &mut state,
                             ) {
                                 error!("intake update error: {}", err);
                             }
+                            state.motor_sample = match (motor.temperature(), motor.current()) {
+                                (Ok(temperature), Ok(current)) => Some(MotorSample {
+                                    temperature,
+                                    current,
+                                }),
+                                _ => None,
+                            };
                         }
                         // Fade the LED!
                         let color = Rgb::from(Hsl::new(
@@ -208,55 +541,182 @@ impl Intake {
     fn update(
         motor: &mut Motor,
         vision: &mut VisionSensor,
-        line_tracker: &mut AdiLineTracker,
-        line_tracker_zero: f64,
+        line_tracker: &mut LineTracker,
         optical: &mut OpticalSensor,
         state: &mut IntakeInner,
     ) -> Result<(), IntakeError> {
+        let belt_moving = motor
+            .velocity()
+            .is_ok_and(|velocity| velocity.abs() > JAM_STALL_VELOCITY);
+        // `None` if the line tracker is missing
+        let ring_detected = line_tracker.ring_detected(belt_moving);
+        let capabilities = IntakeCapabilities {
+            color_sorting: ring_detected.is_some()
+                && (vision.is_connected() || optical.is_connected()),
+            ring_detection: ring_detected.is_some(),
+        };
+        if capabilities != state.capabilities {
+            if capabilities.color_sorting != state.capabilities.color_sorting {
+                if capabilities.color_sorting {
+                    info!("intake color sorting restored");
+                } else {
+                    warn!(
+                        "intake lost color sorting, a color sensor or the line tracker is missing"
+                    );
+                }
+            }
+            if capabilities.ring_detection != state.capabilities.ring_detection {
+                if capabilities.ring_detection {
+                    info!("intake ring detection restored");
+                } else {
+                    warn!("intake lost ring detection, partial intake is now timed");
+                }
+            }
+            state.capabilities = capabilities;
+        }
+
+        if state
+            .give_up_time
+            .is_some_and(|time| time.elapsed() > JAM_GIVE_UP_TIME)
+        {
+            info!("retrying the intake after jam recovery gave up");
+            state.release(IntakePriority::Safety);
+        }
+
         match &mut state.state {
             IntakeState::Forward {
-                ref mut reject_time,
+                ref mut reject,
                 ref mut jam_time,
+                ref mut jam_stage,
                 ref mut overcurrent_time,
+                ref mut stall_time,
                 ref mut current_ring,
                 ref mut red_time,
             } => {
-                if let Ok(current) = motor.current() {
-                    if current > JAM_CURRENT {
-                        if let Some(overcurrent_time) = overcurrent_time {
-                            if overcurrent_time.elapsed() > JAM_OVERCURRENT_TIME {
-                                log::warn!("intake jammed, reversing");
-                                *jam_time = Some(Instant::now());
+                let recovering = if let Some(time) = jam_time
+                    && let Some(stage) = jam_stage
+                {
+                    time.elapsed() < stage.duration()
+                } else {
+                    false
+                };
+                if let Some(time) = jam_time
+                    && time.elapsed() > JAM_ESCALATION_WINDOW
+                {
+                    *jam_time = None;
+                    *jam_stage = None;
+                }
+                let mut jammed = false;
+                if !recovering && reject.is_none() {
+                    if let Ok(current) = motor.current() {
+                        if current > JAM_CURRENT {
+                            if let Some(overcurrent_time) = overcurrent_time {
+                                if overcurrent_time.elapsed() > JAM_OVERCURRENT_TIME {
+                                    log::warn!("intake overcurrent ({:.2} A)", current);
+                                    jammed = true;
+                                }
+                            } else {
+                                *overcurrent_time = Some(Instant::now());
+                            }
+                        } else {
+                            *overcurrent_time = None;
+                        }
+                    } else {
+                        log::warn!("failed to get motor current");
+                    }
+                    if let Ok(velocity) = motor.velocity() {
+                        if velocity.abs() < JAM_STALL_VELOCITY {
+                            if let Some(stall_time) = stall_time {
+                                if stall_time.elapsed() > JAM_STALL_TIME {
+                                    log::warn!("intake stalled ({:.0} RPM)", velocity);
+                                    jammed = true;
+                                }
+                            } else {
+                                *stall_time = Some(Instant::now());
                             }
                         } else {
-                            *overcurrent_time = Some(Instant::now());
+                            *stall_time = None;
                         }
                     } else {
-                        *overcurrent_time = None;
+                        log::warn!("failed to get motor velocity");
                     }
                 } else {
-                    log::warn!("failed to get motor current");
+                    *overcurrent_time = None;
+                    *stall_time = None;
                 }
-                if let Some(jammed_time) = jam_time {
-                    if jammed_time.elapsed() > JAM_REVERSE_TIME {
-                        *jam_time = None;
-                        *overcurrent_time = None;
+                if jammed {
+                    let stage = jam_stage.map_or(JamStage::BackOff, |stage| stage.next());
+                    // Later stages are still the same jam
+                    if jam_stage.is_none() {
+                        state.jam_count += 1;
+                    }
+                    log::warn!(
+                        "intake jammed ({} this match), recovery: {:?}",
+                        state.jam_count,
+                        stage
+                    );
+                    *jam_time = Some(Instant::now());
+                    *jam_stage = Some(stage);
+                    *overcurrent_time = None;
+                    *stall_time = None;
+                    if stage == JamStage::GiveUp {
+                        log::error!("intake jam recovery failed, stopping intake");
+                        state.jam_alert = true;
+                        state.commands[IntakePriority::Safety as usize] = Some(IntakeCommand::Stop);
+                        state.give_up_time = Some(Instant::now());
+                        state.state = IntakeState::Stop;
+                        state.velocity = 0.0;
+                        motor.brake(BrakeMode::Coast).context(MotorSnafu)?;
+                        return Ok(());
                     }
                 }
-                if let Some(reject_time) = reject_time
-                    && (RING_REJECT_STOP_TIME..RING_REJECT_RESTART_TIME)
-                        .contains(&reject_time.elapsed())
-                {
+                match reject {
+                    Some(RejectState::Travelling {
+                        distance,
+                        start,
+                        last_update,
+                    }) => {
+                        // Integrate the measured belt speed so slowed or loaded belts
+                        // still brake when the ring is actually at the apex.
+                        if let Ok(velocity) = motor.velocity() {
+                            *distance += velocity.max(0.0) / 60.0
+                                * BELT_TRAVEL_PER_REV
+                                * last_update.elapsed().as_secs_f64();
+                        }
+                        *last_update = Instant::now();
+                        if *distance >= RING_REJECT_APEX_DISTANCE {
+                            *reject = Some(RejectState::Braking {
+                                start: Instant::now(),
+                            });
+                        } else if start.elapsed() > RING_REJECT_TIMEOUT {
+                            log::warn!(
+                                "rejected ring never reached the hook apex ({:.0} mm)",
+                                distance
+                            );
+                            *reject = None;
+                        }
+                    }
+                    Some(RejectState::Braking { start })
+                        if start.elapsed() > RING_REJECT_BRAKE_TIME =>
+                    {
+                        *reject = None;
+                    }
+                    _ => {}
+                }
+                if matches!(reject, Some(RejectState::Braking { .. })) {
+                    // Ramp back up from a standstill once the ring is off
+                    state.velocity = 0.0;
                     motor.brake(BrakeMode::Brake).context(MotorSnafu)?;
-                } else if jam_time.is_some() {
-                    motor.set_velocity(-RPM).context(MotorSnafu)?;
+                } else if recovering || jammed {
+                    state.velocity = -JAM_RPM as f64;
+                    motor.set_velocity(-JAM_RPM).context(MotorSnafu)?;
                 } else {
-                    motor.set_velocity(RPM).context(MotorSnafu)?;
-                }
-                if let Some(time) = reject_time
-                    && time.elapsed() > RING_REJECT_RESTART_TIME
-                {
-                    *reject_time = None;
+                    let velocity = ramp(
+                        &mut state.velocity,
+                        state.speeds.forward,
+                        state.speeds.acceleration,
+                    );
+                    motor.set_velocity(velocity).context(MotorSnafu)?;
                 }
                 match vision.objects() {
                     Ok(objects) => {
@@ -281,9 +741,11 @@ impl Intake {
                             }
                         }
                     }
-                    Err(err) => {
+                    // A missing sensor is already reported as lost color sorting
+                    Err(err) if vision.is_connected() => {
                         log::warn!("failed to get vision detections: {}", err);
                     }
+                    Err(_) => {}
                 }
                 if let Some(time) = red_time {
                     if time.elapsed() > Duration::from_millis(800) {
@@ -302,67 +764,102 @@ impl Intake {
                     }
                 }
                 if let Some(accept_color) = state.accept {
-                    if reject_time.is_none()
-                        && line_tracker.reflectivity().unwrap_or(0.0)
-                            > line_tracker_zero + LINE_TRACKER_THRESHOLD
+                    if reject.is_none()
+                        && ring_detected == Some(true)
                         && let Some(current_ring) = current_ring
                         && *current_ring != accept_color
                     {
                         log::debug!("intake rejected ring: {:?}", current_ring);
-                        *reject_time = Some(Instant::now());
+                        *reject = Some(RejectState::Travelling {
+                            distance: 0.0,
+                            start: Instant::now(),
+                            last_update: Instant::now(),
+                        });
                     }
                 }
             }
-            IntakeState::PartialIntake => {
-                if line_tracker.reflectivity().unwrap_or(0.0)
-                    < line_tracker_zero + LINE_TRACKER_THRESHOLD
-                {
-                    motor.set_velocity(RPM).context(MotorSnafu)?;
+            IntakeState::PartialIntake { start } => {
+                let done = match ring_detected {
+                    Some(detected) => detected,
+                    None => start.elapsed() > PARTIAL_INTAKE_TIME,
+                };
+                if !done {
+                    let velocity = ramp(
+                        &mut state.velocity,
+                        state.speeds.partial_intake,
+                        state.speeds.acceleration,
+                    );
+                    motor.set_velocity(velocity).context(MotorSnafu)?;
                 } else {
+                    // Finish the request too, so the intake doesn't partial intake
+                    // again when a higher priority releases back to it
+                    if let Some(priority) = state.active_priority()
+                        && state.commands[priority as usize] == Some(IntakeCommand::PartialIntake)
+                    {
+                        state.commands[priority as usize] = Some(IntakeCommand::Stop);
+                    }
                     state.state = IntakeState::Stop;
+                    state.velocity = 0.0;
                     motor.brake(BrakeMode::Brake).context(MotorSnafu)?;
                 }
             }
             IntakeState::Reverse => {
-                motor.set_velocity(-RPM).context(MotorSnafu)?;
+                let velocity = ramp(
+                    &mut state.velocity,
+                    -state.speeds.reverse,
+                    state.speeds.acceleration,
+                );
+                motor.set_velocity(velocity).context(MotorSnafu)?;
             }
             IntakeState::Stop => {
+                state.velocity = 0.0;
                 motor.brake(BrakeMode::Coast).context(MotorSnafu)?;
             }
             IntakeState::StopHold => {
+                state.velocity = 0.0;
                 motor.brake(BrakeMode::Hold).context(MotorSnafu)?;
             }
         }
         Ok(())
     }
 
+    /// Requests `command` on behalf of `priority`. It takes effect immediately
+    /// unless a higher priority request is active.
+    pub fn request(&self, priority: IntakePriority, command: IntakeCommand) {
+        self.state.borrow_mut().request(priority, command);
+    }
+
+    /// Cancels the request made by `priority`, handing control back to the
+    /// next highest request.
+    pub fn release(&self, priority: IntakePriority) {
+        self.state.borrow_mut().release(priority);
+    }
+
+    /// Returns the command requested by `priority`, if any.
+    pub fn command(&self, priority: IntakePriority) -> Option<IntakeCommand> {
+        self.state.borrow().commands[priority as usize]
+    }
+
     pub fn stop(&self) {
-        let mut state = self.state.borrow_mut();
-        state.state = IntakeState::Stop;
+        self.request(IntakePriority::Default, IntakeCommand::Stop);
     }
 
     pub fn stop_hold(&self) {
-        let mut state = self.state.borrow_mut();
-        state.state = IntakeState::StopHold;
+        self.request(IntakePriority::Default, IntakeCommand::StopHold);
     }
 
     pub fn run(&self, direction: Direction) {
-        let mut state = self.state.borrow_mut();
-        state.state = match direction {
-            Direction::Forward => IntakeState::Forward {
-                reject_time: None,
-                jam_time: None,
-                overcurrent_time: None,
-                current_ring: None,
-                red_time: None,
+        self.request(
+            IntakePriority::Default,
+            match direction {
+                Direction::Forward => IntakeCommand::Forward,
+                Direction::Reverse => IntakeCommand::Reverse,
             },
-            Direction::Reverse => IntakeState::Reverse,
-        };
+        );
     }
 
     pub fn partial_intake(&self) {
-        let mut state = self.state.borrow_mut();
-        state.state = IntakeState::PartialIntake;
+        self.request(IntakePriority::Default, IntakeCommand::PartialIntake);
     }
 
     pub fn set_accept(&self, color: Option<RingColor>) {
@@ -373,4 +870,38 @@ impl Intake {
     pub fn accept(&self) -> Option<RingColor> {
         self.state.borrow().accept
     }
+
+    pub fn set_speeds(&self, speeds: IntakeSpeeds) {
+        let mut state = self.state.borrow_mut();
+        state.speeds = speeds;
+    }
+
+    pub fn state(&self) -> IntakeState {
+        self.state.borrow().state
+    }
+
+    /// Returns which features are working with the sensors currently connected.
+    pub fn capabilities(&self) -> IntakeCapabilities {
+        self.state.borrow().capabilities
+    }
+
+    /// Returns the latest temperature and current of the intake motor.
+    pub fn motor_sample(&self) -> Option<MotorSample> {
+        self.state.borrow().motor_sample
+    }
+
+    /// Returns the number of jams detected since the last reset.
+    pub fn jam_count(&self) -> u32 {
+        self.state.borrow().jam_count
+    }
+
+    /// Resets the jam counter, e.g. at the start of a match period.
+    pub fn reset_jam_count(&self) {
+        self.state.borrow_mut().jam_count = 0;
+    }
+
+    /// Returns `true` once after jam recovery gave up so the driver can be notified.
+    pub fn take_jam_alert(&self) -> bool {
+        core::mem::take(&mut self.state.borrow_mut().jam_alert)
+    }
 }
@@ -1,20 +1,95 @@
 use alloc::rc::Rc;
-use core::{cell::RefCell, f64};
+use core::{cell::RefCell, f64, time::Duration};
 
-use log::error;
+use lady_brown_profile::{MotionProfile, ProfileConstraints, FEEDFORWARD};
+use log::{error, info};
 use snafu::{ResultExt, Snafu};
 use vexide::{
+    competition::{self, CompetitionMode},
+    float::Float as _,
     prelude::{sleep, spawn, AdiDigitalIn, Motor, Position},
     task::Task,
+    time::Instant,
 };
 use vexide_motorgroup::{MotorGroup, MotorGroupError};
 
-#[derive(Debug, Clone, Copy)]
+use self::presets::LadyBrownPresets;
+use super::motor_monitor::MotorSample;
+use crate::utils::battery;
+
+pub mod presets;
+#[cfg(feature = "tune_lady_brown")]
+pub mod tuning;
+
+pub use lady_brown_profile::DEFAULT_CONSTRAINTS;
+
+/// Largest error (in degrees) at which [`LadyBrown::move_to`] considers the
+/// arm settled.
+const SETTLE_TOLERANCE: f64 = 3.0;
+/// How long the arm has to stay settled before [`LadyBrown::move_to`] resolves.
+const SETTLE_DURATION: Duration = Duration::from_millis(60);
+/// Largest error (in degrees) at which an arm that stopped moving, e.g. because
+/// it's pushing against a stake, counts as arrived.
+const STALL_TOLERANCE: f64 = 10.0;
+/// Below this arm speed (in degrees per second) the arm has stopped moving.
+const STALL_VELOCITY: f64 = 5.0;
+/// How long the arm has to stay stopped near its target to count as arrived.
+const STALL_DURATION: Duration = Duration::from_millis(200);
+const MOVE_TIMEOUT: Duration = Duration::from_millis(1500);
+
+/// Current (in amps) above which the arm may be overloaded.
+const OVERLOAD_CURRENT: f64 = 2.0;
+/// Error (in degrees) from the setpoint above which a high current means the
+/// arm is blocked rather than just accelerating.
+const OVERLOAD_ERROR: f64 = 10.0;
+/// How long the current and error have to stay high before backing off.
+const OVERLOAD_TIME: Duration = Duration::from_millis(500);
+/// Voltage limit while overloaded, enough to hold the arm against gravity
+/// without burning out the motors.
+const OVERLOAD_HOLD_VOLTAGE: f64 = 2.0;
+
+const HOMING_VOLTAGE: f64 = -4.0;
+const HOMING_TIMEOUT: Duration = Duration::from_millis(3000);
+/// Below this motor velocity (in RPM) the arm is considered stalled while homing.
+const HOMING_STALL_VELOCITY: f64 = 5.0;
+const HOMING_STALL_TIME: Duration = Duration::from_millis(500);
+
+/// PID gains used while moving the arm towards a state.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PidGains {
+    pub kp: f64,
+    pub ki: f64,
+    pub ki_limit: f64,
+    pub kd: f64,
+}
+
+impl PidGains {
+    fn apply(&self, pid: &mut pid::Pid<f64>) {
+        pid.p(self.kp, f64::MAX);
+        pid.i(self.ki, self.ki_limit);
+        pid.d(self.kd, f64::MAX);
+        pid.reset_integral_term();
+    }
+}
+
+pub const DEFAULT_GAINS: PidGains = PidGains {
+    kp: 0.5,
+    ki: 0.005,
+    ki_limit: 0.3,
+    kd: 0.11,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum LadyBrownState {
     Initial,
     Intake,
     MaxExpansion,
     Manual(f64),
+    /// A named position from [`LadyBrownPresets`].
+    Preset {
+        index: usize,
+        angle: f64,
+    },
 }
 
 impl Default for LadyBrownState {
@@ -34,6 +109,36 @@ impl LadyBrownState {
             Self::MaxExpansion => Self::MAX_EXPANSION_ANGLE,
             Self::Initial => Self::INITIAL_ARM_ANGLE,
             Self::Manual(angle) => *angle,
+            Self::Preset { angle, .. } => *angle,
+        }
+    }
+
+    /// Gains scheduled for moving to this state. Feedforward handles gravity,
+    /// so these only need to correct for the remaining error.
+    pub fn gains(&self) -> PidGains {
+        match self {
+            // Gravity helps on the way down, and homing takes over at the bottom
+            Self::Initial => PidGains {
+                kp: 0.4,
+                ki: 0.0,
+                ki_limit: 0.0,
+                kd: 0.1,
+            },
+            // Loading needs to be precise so rings seat on the arm
+            Self::Intake => PidGains {
+                kp: 0.5,
+                ki: 0.01,
+                ki_limit: 0.5,
+                kd: 0.11,
+            },
+            // Scoring pushes against the stake
+            Self::MaxExpansion => PidGains {
+                kp: 0.6,
+                ki: 0.005,
+                ki_limit: 0.3,
+                kd: 0.13,
+            },
+            Self::Manual(_) | Self::Preset { .. } => DEFAULT_GAINS,
         }
     }
 
@@ -43,14 +148,43 @@ impl LadyBrownState {
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+struct Homing {
+    start: Instant,
+    stall_time: Option<Instant>,
+}
+
+impl Homing {
+    fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            stall_time: None,
+        }
+    }
+}
+
 #[derive(Debug)]
 struct LadyBrownInner {
     state: LadyBrownState,
+    angle: f64,
+    profile: MotionProfile,
+    gains: PidGains,
+    homing: Option<Homing>,
+    /// Whether the arm has been homed since it last entered [`LadyBrownState::Initial`].
+    homed: bool,
+    fault: Option<HomingError>,
+    /// Encoder drift (in degrees) corrected by the last successful home.
+    last_drift: Option<f64>,
+    overcurrent_time: Option<Instant>,
+    /// Target angle the arm was blocked from reaching, while overloaded.
+    overload: Option<f64>,
+    motor_sample: Option<MotorSample>,
 }
 
 #[derive(Debug, Clone)]
 pub struct LadyBrown {
     inner: Rc<RefCell<LadyBrownInner>>,
+    presets: Rc<LadyBrownPresets>,
     _task: Rc<Task<()>>,
 }
 
@@ -62,25 +196,62 @@ pub enum LadyBrownError {
     MotorGet,
 }
 
+/// Reasons [`LadyBrown::move_to`] failed.
+#[derive(Debug, Clone, Copy, PartialEq, Snafu)]
+pub enum MoveError {
+    #[snafu(display("arm did not reach {:.1} deg in time (at {:.1} deg)", target, angle))]
+    Timeout { target: f64, angle: f64 },
+    #[snafu(display("arm was sent elsewhere before it arrived"))]
+    Interrupted,
+}
+
+/// Reasons the arm failed to home. Once faulted, the arm is no longer homed
+/// automatically until [`LadyBrown::home`] is called.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Snafu)]
+pub enum HomingError {
+    #[snafu(display("limit switch never triggered"))]
+    Timeout,
+    #[snafu(display("arm stalled before reaching the limit switch"))]
+    Stalled,
+    #[snafu(display("failed to read the limit switch"))]
+    LimitSwitch,
+    /// The arm was sent somewhere else before it got home, which doesn't
+    /// fault it.
+    #[snafu(display("arm was moved away before it got home"))]
+    Cancelled,
+}
+
 impl LadyBrown {
     pub fn new(
         mut motors: MotorGroup,
         gear_ratio: f64,
         limit: AdiDigitalIn,
-    ) -> Result<Self, LadyBrownError> {
-        motors
-            .set_position(Position::from_degrees(
-                LadyBrownState::Initial.angle() * gear_ratio,
-            ))
-            .context(MotorSnafu)?;
+        presets: LadyBrownPresets,
+    ) -> Self {
+        // Homing corrects the position later, so a missing motor shouldn't stop
+        // the rest of the robot from starting
+        if let Err(err) = motors.set_position(Position::from_degrees(
+            LadyBrownState::Initial.angle() * gear_ratio,
+        )) {
+            log::warn!("failed to zero the arm: {}", err);
+        }
 
         let mut pid = pid::Pid::new(LadyBrownState::Initial.angle(), Motor::EXP_MAX_VOLTAGE);
-        pid.p(0.5, f64::MAX);
-        pid.i(0.005, 0.3);
-        pid.d(0.11, f64::MAX);
+        let gains = LadyBrownState::Initial.gains();
+        gains.apply(&mut pid);
 
         let inner = Rc::new(RefCell::new(LadyBrownInner {
             state: LadyBrownState::default(),
+            angle: LadyBrownState::Initial.angle(),
+            profile: MotionProfile::new(DEFAULT_CONSTRAINTS, LadyBrownState::Initial.angle()),
+            gains,
+            homing: None,
+            homed: false,
+            fault: None,
+            last_drift: None,
+            overcurrent_time: None,
+            overload: None,
+            motor_sample: None,
         }));
         let inner_clone = inner.clone();
         let task = spawn(async move {
@@ -90,19 +261,28 @@ impl LadyBrown {
                     &mut pid,
                     &limit,
                     gear_ratio,
-                    &mut inner_clone.borrow_mut().state,
+                    &mut inner_clone.borrow_mut(),
                 ) {
                     error!("arm update error: {}", err);
                 }
+                inner_clone.borrow_mut().motor_sample =
+                    match (motors.temperature(), motors.current()) {
+                        (Ok(temperature), Ok(current)) => Some(MotorSample {
+                            temperature,
+                            current,
+                        }),
+                        _ => None,
+                    };
 
                 sleep(super::SUBSYSTEM_UPDATE_PERIOD).await;
             }
         });
 
-        Ok(Self {
+        Self {
             inner,
+            presets: Rc::new(presets),
             _task: Rc::new(task),
-        })
+        }
     }
 
     fn update(
@@ -110,27 +290,128 @@ impl LadyBrown {
         pid: &mut pid::Pid<f64>,
         limit: &AdiDigitalIn,
         gear_ratio: f64,
-        state: &mut LadyBrownState,
+        inner: &mut LadyBrownInner,
     ) -> Result<(), LadyBrownError> {
-        pid.setpoint = state.angle();
+        let state = inner.state;
+        let gains = state.gains();
+        if gains != inner.gains {
+            gains.apply(pid);
+            inner.gains = gains;
+        }
+        let setpoint = inner
+            .profile
+            .step(state.angle(), super::SUBSYSTEM_UPDATE_PERIOD.as_secs_f64());
+        pid.setpoint = setpoint.position;
         let current_angle: f64 = motors
             .position()
             .map_err(|_| LadyBrownError::MotorGet)?
             .as_degrees()
             / gear_ratio;
-        let output = pid.next_control_output(current_angle);
-        motors.set_voltage(output.output).context(MotorSnafu)?;
-        if matches!(state, LadyBrownState::Initial)
-            && current_angle <= LadyBrownState::INITIAL_ARM_ANGLE + 5.0
-        {
-            if limit.is_high().unwrap_or(true) {
-                motors
-                    .set_position(Position::from_degrees(
-                        LadyBrownState::INITIAL_ARM_ANGLE * gear_ratio,
-                    ))
-                    .context(MotorSnafu)?;
+        inner.angle = current_angle;
+        let mut output = ((pid.next_control_output(current_angle).output
+            + FEEDFORWARD.calculate(setpoint))
+            * battery::compensation())
+        .clamp(-Motor::EXP_MAX_VOLTAGE, Motor::EXP_MAX_VOLTAGE);
+
+        let error = setpoint.position - current_angle;
+        if inner.overload.is_none() && inner.homing.is_none() {
+            match motors.current() {
+                Ok(current) if current > OVERLOAD_CURRENT && error.abs() > OVERLOAD_ERROR => {
+                    if inner
+                        .overcurrent_time
+                        .get_or_insert_with(Instant::now)
+                        .elapsed()
+                        > OVERLOAD_TIME
+                    {
+                        error!(
+                            "arm overloaded ({:.2} A, {:.1} deg from setpoint), backing off",
+                            current, error
+                        );
+                        pid.reset_integral_term();
+                        inner.overload = Some(state.angle());
+                    }
+                }
+                Ok(_) => inner.overcurrent_time = None,
+                Err(err) => log::warn!("failed to get arm current: {}", err),
+            }
+        }
+        if let Some(target) = inner.overload {
+            if target != state.angle() || error.abs() < SETTLE_TOLERANCE {
+                info!("arm overload cleared");
+                inner.overload = None;
+                inner.overcurrent_time = None;
             } else {
-                motors.set_voltage(-4.0).context(MotorSnafu)?;
+                output = output.clamp(-OVERLOAD_HOLD_VOLTAGE, OVERLOAD_HOLD_VOLTAGE);
+            }
+        }
+        motors.set_voltage(output).context(MotorSnafu)?;
+        if !matches!(state, LadyBrownState::Initial) {
+            inner.homed = false;
+            // Moving the arm elsewhere stops driving it down to the switch
+            if inner.homing.take().is_some() {
+                info!("arm homing cancelled");
+            }
+        } else if !inner.homed
+            && inner.homing.is_none()
+            && inner.fault.is_none()
+            && (current_angle <= LadyBrownState::INITIAL_ARM_ANGLE + 5.0
+                || inner.profile.is_finished(LadyBrownState::INITIAL_ARM_ANGLE))
+        {
+            inner.homing = Some(Homing::new());
+        }
+        if let Some(homing) = &mut inner.homing {
+            if matches!(competition::mode(), CompetitionMode::Disabled) {
+                // Motors don't move while disabled, so don't count it against the timeout
+                *homing = Homing::new();
+            }
+            let result = match limit.is_high() {
+                Ok(true) => Ok(true),
+                Ok(false) if homing.start.elapsed() > HOMING_TIMEOUT => Err(HomingError::Timeout),
+                Ok(false) => {
+                    let velocity = motors.velocity().map_err(|_| LadyBrownError::MotorGet)?;
+                    if velocity.abs() < HOMING_STALL_VELOCITY {
+                        match homing.stall_time {
+                            Some(time) if time.elapsed() > HOMING_STALL_TIME => {
+                                Err(HomingError::Stalled)
+                            }
+                            Some(_) => Ok(false),
+                            None => {
+                                homing.stall_time = Some(Instant::now());
+                                Ok(false)
+                            }
+                        }
+                    } else {
+                        homing.stall_time = None;
+                        Ok(false)
+                    }
+                }
+                Err(_) => Err(HomingError::LimitSwitch),
+            };
+            match result {
+                Ok(true) => {
+                    let drift = current_angle - LadyBrownState::INITIAL_ARM_ANGLE;
+                    motors
+                        .set_position(Position::from_degrees(
+                            LadyBrownState::INITIAL_ARM_ANGLE * gear_ratio,
+                        ))
+                        .context(MotorSnafu)?;
+                    inner.profile.reset(LadyBrownState::INITIAL_ARM_ANGLE);
+                    pid.reset_integral_term();
+                    info!("arm homed, corrected {:.1} deg of encoder drift", drift);
+                    inner.last_drift = Some(drift);
+                    inner.homed = true;
+                    inner.homing = None;
+                }
+                Ok(false) => {
+                    motors.set_voltage(HOMING_VOLTAGE).context(MotorSnafu)?;
+                }
+                Err(fault) => {
+                    error!("arm homing failed: {}", fault);
+                    motors.set_voltage(0.0).context(MotorSnafu)?;
+                    inner.profile.reset(current_angle);
+                    inner.fault = Some(fault);
+                    inner.homing = None;
+                }
             }
         }
         Ok(())
@@ -147,4 +428,120 @@ impl LadyBrown {
     pub fn set_state(&self, state: LadyBrownState) {
         self.inner.borrow_mut().state = state;
     }
+
+    /// Sets the velocity and acceleration limits used when moving between states.
+    #[cfg(feature = "tune_lady_brown")]
+    pub fn set_constraints(&self, constraints: ProfileConstraints) {
+        self.inner.borrow_mut().profile.set_constraints(constraints);
+    }
+
+    pub fn presets(&self) -> &LadyBrownPresets {
+        &self.presets
+    }
+
+    /// Returns the state for the preset called `name`, logging if it doesn't exist.
+    pub fn preset(&self, name: &str) -> Option<LadyBrownState> {
+        let state = self.presets.get(name);
+        if state.is_none() {
+            log::warn!("unknown lady brown preset: {}", name);
+        }
+        state
+    }
+
+    /// Moves the arm to `state`, resolving once it has settled there or stopped
+    /// close to it.
+    pub async fn move_to(&self, state: LadyBrownState) -> Result<(), MoveError> {
+        self.move_to_with_timeout(state, MOVE_TIMEOUT).await
+    }
+
+    pub async fn move_to_with_timeout(
+        &self,
+        state: LadyBrownState,
+        timeout: Duration,
+    ) -> Result<(), MoveError> {
+        self.set_state(state);
+        let start = Instant::now();
+        let mut settled_since: Option<Instant> = None;
+        let mut stalled_since: Option<Instant> = None;
+        let mut last_angle = self.angle();
+        loop {
+            sleep(super::SUBSYSTEM_UPDATE_PERIOD).await;
+            let inner = self.inner.borrow();
+            if inner.state != state {
+                return Err(MoveError::Interrupted);
+            }
+            let error = (state.angle() - inner.angle).abs();
+            let speed =
+                (inner.angle - last_angle).abs() / super::SUBSYSTEM_UPDATE_PERIOD.as_secs_f64();
+            last_angle = inner.angle;
+            let finished = inner.profile.is_finished(state.angle());
+            if finished && error < SETTLE_TOLERANCE {
+                if settled_since.get_or_insert_with(Instant::now).elapsed() >= SETTLE_DURATION {
+                    return Ok(());
+                }
+            } else {
+                settled_since = None;
+            }
+            if finished && error < STALL_TOLERANCE && speed < STALL_VELOCITY {
+                if stalled_since.get_or_insert_with(Instant::now).elapsed() >= STALL_DURATION {
+                    info!(
+                        "arm stopped {:.1} deg from {:.1} deg, treating it as arrived",
+                        error,
+                        state.angle()
+                    );
+                    return Ok(());
+                }
+            } else {
+                stalled_since = None;
+            }
+            if start.elapsed() > timeout {
+                return Err(MoveError::Timeout {
+                    target: state.angle(),
+                    angle: inner.angle,
+                });
+            }
+        }
+    }
+
+    /// Drives the arm down until the limit switch triggers and re-zeros the
+    /// encoder, returning the drift (in degrees) that was corrected.
+    pub async fn home(&self) -> Result<f64, HomingError> {
+        {
+            let mut inner = self.inner.borrow_mut();
+            inner.state = LadyBrownState::Initial;
+            inner.fault = None;
+            inner.homing = Some(Homing::new());
+        }
+        loop {
+            sleep(super::SUBSYSTEM_UPDATE_PERIOD).await;
+            let inner = self.inner.borrow();
+            if inner.homing.is_none() {
+                return match (inner.homed, inner.fault) {
+                    (true, _) => Ok(inner.last_drift.unwrap_or_default()),
+                    (false, Some(fault)) => Err(fault),
+                    (false, None) => Err(HomingError::Cancelled),
+                };
+            }
+        }
+    }
+
+    /// Returns the reason the last homing attempt failed, if it did.
+    pub fn fault(&self) -> Option<HomingError> {
+        self.inner.borrow().fault
+    }
+
+    /// Returns `true` while the arm is backed off because it was blocked.
+    pub fn is_overloaded(&self) -> bool {
+        self.inner.borrow().overload.is_some()
+    }
+
+    /// Returns the measured arm angle in degrees.
+    pub fn angle(&self) -> f64 {
+        self.inner.borrow().angle
+    }
+
+    /// Returns the latest temperature and current of the arm motors.
+    pub fn motor_sample(&self) -> Option<MotorSample> {
+        self.inner.borrow().motor_sample
+    }
 }
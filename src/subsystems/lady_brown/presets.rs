@@ -0,0 +1,162 @@
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use log::{info, warn};
+use snafu::Snafu;
+
+use super::LadyBrownState;
+
+/// A named arm position.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LadyBrownPreset {
+    pub name: String,
+    /// Arm angle in degrees.
+    pub angle: f64,
+}
+
+#[derive(Debug, Snafu)]
+pub enum PresetParseError {
+    #[snafu(display("line {}: expected `name = angle`", line))]
+    InvalidLine { line: usize },
+    #[snafu(display("line {}: invalid angle `{}`", line, angle))]
+    InvalidAngle { line: usize, angle: String },
+    #[snafu(display(
+        "line {}: angle {} is outside {}..={}",
+        line,
+        angle,
+        LadyBrownState::INITIAL_ARM_ANGLE,
+        LadyBrownState::MAX_EXPANSION_ANGLE
+    ))]
+    AngleOutOfRange { line: usize, angle: f64 },
+    #[snafu(display("no presets defined"))]
+    Empty,
+}
+
+/// Table of named arm positions, loaded from [`LadyBrownPresets::CONFIG_PATH`]
+/// on the SD card so angles can be changed without recompiling.
+///
+/// The file has one `name = angle` pair per line. Blank lines and lines
+/// starting with `#` are ignored. Angles have to be within the arm's range of
+/// motion, from [`LadyBrownState::INITIAL_ARM_ANGLE`] to
+/// [`LadyBrownState::MAX_EXPANSION_ANGLE`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LadyBrownPresets {
+    presets: Vec<LadyBrownPreset>,
+}
+
+impl LadyBrownPresets {
+    pub const CONFIG_PATH: &str = "lady_brown.cfg";
+
+    pub fn defaults() -> Self {
+        Self {
+            presets: [
+                ("alliance stake", LadyBrownState::MAX_EXPANSION_ANGLE),
+                ("wall stake", LadyBrownState::INITIAL_ARM_ANGLE + 130.0),
+                ("descore", LadyBrownState::INITIAL_ARM_ANGLE + 145.0),
+                ("tip hold", LadyBrownState::INITIAL_ARM_ANGLE + 90.0),
+            ]
+            .into_iter()
+            .map(|(name, angle)| LadyBrownPreset {
+                name: name.to_string(),
+                angle,
+            })
+            .collect(),
+        }
+    }
+
+    pub fn parse(source: &str) -> Result<Self, PresetParseError> {
+        let mut presets = Vec::new();
+        for (i, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, angle) = line
+                .split_once('=')
+                .ok_or(PresetParseError::InvalidLine { line: i + 1 })?;
+            let angle = angle.trim();
+            let angle: f64 = angle
+                .parse()
+                .ok()
+                .filter(|angle: &f64| angle.is_finite())
+                .ok_or_else(|| PresetParseError::InvalidAngle {
+                    line: i + 1,
+                    angle: angle.to_string(),
+                })?;
+            if !(LadyBrownState::INITIAL_ARM_ANGLE..=LadyBrownState::MAX_EXPANSION_ANGLE)
+                .contains(&angle)
+            {
+                return Err(PresetParseError::AngleOutOfRange { line: i + 1, angle });
+            }
+            presets.push(LadyBrownPreset {
+                name: name.trim().to_string(),
+                angle,
+            });
+        }
+        if presets.is_empty() {
+            return Err(PresetParseError::Empty);
+        }
+        Ok(Self { presets })
+    }
+
+    /// Loads presets from the SD card, falling back to [`Self::defaults`] if the
+    /// file is missing or invalid.
+    pub fn load() -> Self {
+        match vexide::fs::read_to_string(Self::CONFIG_PATH) {
+            Ok(source) => match Self::parse(&source) {
+                Ok(presets) => {
+                    info!(
+                        "loaded {} lady brown presets from {}",
+                        presets.presets.len(),
+                        Self::CONFIG_PATH
+                    );
+                    presets
+                }
+                Err(err) => {
+                    warn!("invalid {}, using defaults: {}", Self::CONFIG_PATH, err);
+                    Self::defaults()
+                }
+            },
+            Err(_) => Self::defaults(),
+        }
+    }
+
+    /// Returns the state for the preset at `index`.
+    pub fn state(&self, index: usize) -> Option<LadyBrownState> {
+        self.presets
+            .get(index)
+            .map(|preset| LadyBrownState::Preset {
+                index,
+                angle: preset.angle,
+            })
+    }
+
+    /// Returns the state for the preset called `name`.
+    pub fn get(&self, name: &str) -> Option<LadyBrownState> {
+        self.presets
+            .iter()
+            .position(|preset| preset.name == name)
+            .and_then(|index| self.state(index))
+    }
+
+    /// Returns the preset after `current`, wrapping around, or the first
+    /// preset if `current` isn't one.
+    pub fn next(&self, current: LadyBrownState) -> Option<LadyBrownState> {
+        match current {
+            LadyBrownState::Preset { index, .. } => self.state((index + 1) % self.presets.len()),
+            _ => self.state(0),
+        }
+    }
+
+    /// Returns the name of `state` if it is a preset.
+    pub fn name(&self, state: LadyBrownState) -> Option<&str> {
+        match state {
+            LadyBrownState::Preset { index, .. } => {
+                self.presets.get(index).map(|preset| preset.name.as_str())
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use alloc::{format, string::String, vec::Vec};
+use core::time::Duration;
+
+use lady_brown_profile::ProfileConstraints;
+use log::info;
+use vexide::{
+    float::Float as _,
+    prelude::{sleep, Display},
+    time::Instant,
+};
+
+use super::{LadyBrown, LadyBrownState, DEFAULT_CONSTRAINTS};
+use crate::graphics;
+
+/// Setpoints the arm is stepped through, covering both directions of each move.
+pub const TUNING_SETPOINTS: [LadyBrownState; 6] = [
+    LadyBrownState::Intake,
+    LadyBrownState::MaxExpansion,
+    LadyBrownState::Intake,
+    LadyBrownState::Initial,
+    LadyBrownState::MaxExpansion,
+    LadyBrownState::Initial,
+];
+/// Limits the setpoints are stepped through with, to see whether a faster
+/// profile still settles.
+pub const TUNING_CONSTRAINTS: [ProfileConstraints; 2] = [
+    DEFAULT_CONSTRAINTS,
+    ProfileConstraints {
+        max_velocity: 600.0,
+        max_acceleration: 3000.0,
+    },
+];
+const STEP_DURATION: Duration = Duration::from_millis(1500);
+/// The arm is settled once it stays within this many degrees of the target.
+const SETTLE_BAND: f64 = 2.0;
+/// Steady-state error is averaged over the end of each step.
+const STEADY_STATE_WINDOW: Duration = Duration::from_millis(200);
+
+/// Measured response of the arm to a single setpoint change.
+#[derive(Debug, Clone, Copy)]
+pub struct StepResponse {
+    pub state: LadyBrownState,
+    /// Degrees past the target in the direction of travel.
+    pub overshoot: f64,
+    /// Time until the arm stayed within [`SETTLE_BAND`], or `None` if it never did.
+    pub settling_time: Option<Duration>,
+    /// Mean error (in degrees) at the end of the step.
+    pub steady_state_error: f64,
+}
+
+impl StepResponse {
+    /// Moves `lady_brown` to `state` and records how it gets there.
+    pub async fn measure(lady_brown: &LadyBrown, state: LadyBrownState) -> Self {
+        let target = state.angle();
+        let direction = (target - lady_brown.angle()).signum();
+        lady_brown.set_state(state);
+
+        let start = Instant::now();
+        let mut overshoot: f64 = 0.0;
+        let mut last_unsettled = Duration::ZERO;
+        let mut error_sum = 0.0;
+        let mut error_samples = 0;
+        let mut error = 0.0;
+        while start.elapsed() < STEP_DURATION {
+            error = target - lady_brown.angle();
+            overshoot = overshoot.max(-error * direction);
+            if error.abs() > SETTLE_BAND {
+                last_unsettled = start.elapsed();
+            }
+            if start.elapsed() > STEP_DURATION - STEADY_STATE_WINDOW {
+                error_sum += error;
+                error_samples += 1;
+            }
+            sleep(crate::subsystems::SUBSYSTEM_UPDATE_PERIOD).await;
+        }
+
+        Self {
+            state,
+            overshoot,
+            settling_time: (error.abs() <= SETTLE_BAND).then_some(last_unsettled),
+            steady_state_error: if error_samples > 0 {
+                error_sum / error_samples as f64
+            } else {
+                error
+            },
+        }
+    }
+}
+
+impl core::fmt::Display for StepResponse {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{:?}: overshoot {:.1}deg, settled ",
+            self.state, self.overshoot
+        )?;
+        match self.settling_time {
+            Some(time) => write!(f, "{}ms", time.as_millis())?,
+            None => write!(f, "never")?,
+        }
+        write!(f, ", error {:.2}deg", self.steady_state_error)
+    }
+}
+
+/// Steps the arm through [`TUNING_SETPOINTS`] with each of
+/// [`TUNING_CONSTRAINTS`], reporting each response to the brain screen and the
+/// log.
+pub async fn tune(lady_brown: &LadyBrown, display: &mut Display) -> Vec<StepResponse> {
+    let mut responses = Vec::new();
+    for constraints in TUNING_CONSTRAINTS {
+        let header = format!(
+            "Lady brown tuning: {:.0} deg/s, {:.0} deg/s^2",
+            constraints.max_velocity, constraints.max_acceleration
+        );
+        info!("{}", header);
+        let mut lines: Vec<String> = Vec::new();
+        lines.push(header);
+        graphics::render_text(display, &lines);
+        lady_brown.set_constraints(constraints);
+        for state in TUNING_SETPOINTS {
+            let response = StepResponse::measure(lady_brown, state).await;
+            info!("lady brown tuning: {}", response);
+            lines.push(format!("{}", response));
+            graphics::render_text(display, &lines);
+            responses.push(response);
+        }
+    }
+    lady_brown.set_constraints(DEFAULT_CONSTRAINTS);
+    lady_brown.set_state(LadyBrownState::Initial);
+    responses
+}
@@ -0,0 +1,174 @@
+use alloc::{boxed::Box, rc::Rc, vec::Vec};
+use core::{cell::RefCell, time::Duration};
+
+use log::{info, warn};
+use vexide::{
+    prelude::{sleep, spawn},
+    task::Task,
+    time::Instant,
+};
+
+/// How often motors are sampled.
+const SAMPLE_PERIOD: Duration = Duration::from_secs(1);
+/// How often temperatures are written to the log.
+const LOG_PERIOD: Duration = Duration::from_secs(30);
+/// Motors at or above this temperature (in °C) alert the driver.
+pub const WARNING_TEMPERATURE: f64 = 50.0;
+/// Drive voltage is derated linearly from [`WARNING_TEMPERATURE`] down to
+/// [`MIN_DERATE`] at this temperature, where the firmware starts limiting
+/// current anyway.
+const DERATE_TEMPERATURE: f64 = 55.0;
+const MIN_DERATE: f64 = 0.6;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MotorSample {
+    /// Temperature in °C.
+    pub temperature: f64,
+    /// Current draw in A.
+    pub current: f64,
+}
+
+/// A motor or motor group to watch.
+pub struct MonitoredMotor {
+    pub name: &'static str,
+    /// Whether the drive voltage should be derated when this motor is hot.
+    pub drive: bool,
+    pub sample: Box<dyn Fn() -> Option<MotorSample>>,
+}
+
+impl MonitoredMotor {
+    pub fn new(
+        name: &'static str,
+        drive: bool,
+        sample: impl Fn() -> Option<MotorSample> + 'static,
+    ) -> Self {
+        Self {
+            name,
+            drive,
+            sample: Box::new(sample),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MotorStatus {
+    pub name: &'static str,
+    pub sample: Option<MotorSample>,
+    /// Temperature at the last log, to show the trend.
+    logged_temperature: Option<f64>,
+    warned: bool,
+}
+
+struct MotorMonitorInner {
+    motors: Vec<MotorStatus>,
+    derate: f64,
+    alert: Option<&'static str>,
+}
+
+/// Samples the temperature and current of every motor in the background,
+/// alerting when one gets hot.
+#[derive(Clone)]
+pub struct MotorMonitor {
+    inner: Rc<RefCell<MotorMonitorInner>>,
+    _task: Rc<Task<()>>,
+}
+
+impl MotorMonitor {
+    pub fn new(motors: Vec<MonitoredMotor>) -> Self {
+        let inner = Rc::new(RefCell::new(MotorMonitorInner {
+            motors: motors
+                .iter()
+                .map(|motor| MotorStatus {
+                    name: motor.name,
+                    sample: None,
+                    logged_temperature: None,
+                    warned: false,
+                })
+                .collect(),
+            derate: 1.0,
+            alert: None,
+        }));
+        let task = spawn({
+            let inner = inner.clone();
+            async move {
+                let mut last_log = Instant::now();
+                loop {
+                    let log_now = last_log.elapsed() >= LOG_PERIOD;
+                    if log_now {
+                        last_log = Instant::now();
+                    }
+                    Self::update(&motors, &mut inner.borrow_mut(), log_now);
+                    sleep(SAMPLE_PERIOD).await;
+                }
+            }
+        });
+        Self {
+            inner,
+            _task: Rc::new(task),
+        }
+    }
+
+    fn update(motors: &[MonitoredMotor], inner: &mut MotorMonitorInner, log_now: bool) {
+        let mut hottest_drive: f64 = 0.0;
+        for (motor, status) in motors.iter().zip(inner.motors.iter_mut()) {
+            status.sample = (motor.sample)();
+            let Some(sample) = status.sample else {
+                continue;
+            };
+            if motor.drive {
+                hottest_drive = hottest_drive.max(sample.temperature);
+            }
+            if log_now {
+                info!(
+                    "{}: {:.0} C ({:+.0} C), {:.2} A",
+                    motor.name,
+                    sample.temperature,
+                    sample.temperature - status.logged_temperature.unwrap_or(sample.temperature),
+                    sample.current
+                );
+                status.logged_temperature = Some(sample.temperature);
+            }
+            if sample.temperature >= WARNING_TEMPERATURE && !status.warned {
+                status.warned = true;
+                warn!(
+                    "{} is overheating ({:.0} C)",
+                    motor.name, sample.temperature
+                );
+                inner.alert = Some(motor.name);
+            } else if sample.temperature < WARNING_TEMPERATURE {
+                status.warned = false;
+            }
+        }
+        inner.derate = 1.0
+            - ((hottest_drive - WARNING_TEMPERATURE) / (DERATE_TEMPERATURE - WARNING_TEMPERATURE))
+                .clamp(0.0, 1.0)
+                * (1.0 - MIN_DERATE);
+    }
+
+    /// Returns the latest sample of every motor.
+    pub fn motors(&self) -> Vec<MotorStatus> {
+        self.inner.borrow().motors.clone()
+    }
+
+    /// Returns the hottest motor at or above [`WARNING_TEMPERATURE`], if any.
+    pub fn hottest(&self) -> Option<(&'static str, f64)> {
+        self.inner
+            .borrow()
+            .motors
+            .iter()
+            .filter_map(|motor| Some((motor.name, motor.sample?.temperature)))
+            .filter(|(_, temperature)| *temperature >= WARNING_TEMPERATURE)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    /// Returns the name of a motor that just started overheating, once.
+    pub fn take_alert(&self) -> Option<&'static str> {
+        self.inner.borrow_mut().alert.take()
+    }
+
+    /// Returns the fraction of the full drive voltage that should be used to
+    /// keep the drive motors from overheating.
+    pub fn drive_derate(&self) -> f64 {
+        self.inner.borrow().derate
+    }
+}
@@ -0,0 +1,151 @@
+use alloc::rc::Rc;
+use core::{cell::RefCell, fmt, time::Duration};
+
+use vexide::{prelude::sleep, time::Instant};
+
+pub type SinglePneumatic = libdoxa::subsystems::pneumatic::PneumaticSubsystem<1, false>;
+
+/// How long a cylinder takes to fully extend or retract after being commanded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PneumaticTiming {
+    pub extend: Duration,
+    pub retract: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PneumaticState {
+    Extending,
+    Extended,
+    Retracting,
+    Retracted,
+}
+
+impl fmt::Display for PneumaticState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PneumaticState::Extending => "extending",
+            PneumaticState::Extended => "extended",
+            PneumaticState::Retracting => "retracting",
+            PneumaticState::Retracted => "retracted",
+        })
+    }
+}
+
+#[derive(Debug)]
+struct TimedPneumaticInner {
+    changed_at: Instant,
+    /// Time the cylinder needs to finish the current change of state.
+    travel_time: Duration,
+}
+
+/// Wraps a pneumatic subsystem to track whether the cylinder has finished
+/// moving, based on its measured actuation times.
+///
+/// There is no sensor on the cylinders, so the state is only an estimate. A
+/// change of direction while in transit is assumed to take as long as the
+/// distance already travelled.
+#[derive(Clone)]
+pub struct TimedPneumatic {
+    pneumatic: SinglePneumatic,
+    timing: PneumaticTiming,
+    inner: Rc<RefCell<TimedPneumaticInner>>,
+}
+
+impl TimedPneumatic {
+    pub fn new(pneumatic: SinglePneumatic, timing: PneumaticTiming) -> Self {
+        Self {
+            pneumatic,
+            timing,
+            inner: Rc::new(RefCell::new(TimedPneumaticInner {
+                changed_at: Instant::now(),
+                travel_time: Duration::ZERO,
+            })),
+        }
+    }
+
+    fn remaining(&self) -> Duration {
+        let inner = self.inner.borrow();
+        inner.travel_time.saturating_sub(inner.changed_at.elapsed())
+    }
+
+    fn record_change(&self, extending: bool) {
+        let full = if extending {
+            self.timing.extend
+        } else {
+            self.timing.retract
+        };
+        // Reversing mid-stroke only has to undo the part already travelled
+        let remaining = self.remaining();
+        let previous = if extending {
+            self.timing.retract
+        } else {
+            self.timing.extend
+        };
+        let travelled = if previous.is_zero() {
+            1.0
+        } else {
+            1.0 - remaining.as_secs_f64() / previous.as_secs_f64()
+        };
+        let mut inner = self.inner.borrow_mut();
+        inner.changed_at = Instant::now();
+        inner.travel_time = full.mul_f64(travelled);
+    }
+
+    pub fn state(&self) -> PneumaticState {
+        let moving = !self.remaining().is_zero();
+        match (self.pneumatic.extended(), moving) {
+            (true, true) => PneumaticState::Extending,
+            (true, false) => PneumaticState::Extended,
+            (false, true) => PneumaticState::Retracting,
+            (false, false) => PneumaticState::Retracted,
+        }
+    }
+
+    /// Returns the commanded state, which may not have been reached yet. See
+    /// [`Self::state`].
+    pub fn extended(&self) -> bool {
+        self.pneumatic.extended()
+    }
+
+    pub fn extend(&mut self) {
+        if !self.pneumatic.extended() {
+            self.record_change(true);
+        }
+        self.pneumatic.extend();
+    }
+
+    pub fn retract(&mut self) {
+        if self.pneumatic.extended() {
+            self.record_change(false);
+        }
+        self.pneumatic.retract();
+    }
+
+    pub fn toggle(&mut self) {
+        if self.pneumatic.extended() {
+            self.retract();
+        } else {
+            self.extend();
+        }
+    }
+
+    /// Waits until the cylinder has finished moving.
+    pub async fn settle(&self) {
+        let remaining = self.remaining();
+        if !remaining.is_zero() {
+            sleep(remaining).await;
+        }
+    }
+
+    /// Extends and waits until the cylinder is fully extended.
+    pub async fn extend_and_wait(&mut self) {
+        self.extend();
+        self.settle().await;
+    }
+
+    /// Retracts and waits until the cylinder is fully retracted.
+    pub async fn retract_and_wait(&mut self) {
+        self.retract();
+        self.settle().await;
+    }
+}
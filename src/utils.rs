@@ -1 +1,2 @@
+pub mod battery;
 pub mod logger;
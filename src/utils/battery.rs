@@ -0,0 +1,28 @@
+use vexide::devices::battery;
+
+/// Battery voltage the drive and arm were tuned at.
+const NOMINAL_VOLTAGE: f64 = 12.8;
+/// Limits on the compensation so a bad reading can't send full voltage.
+const MIN_COMPENSATION: f64 = 0.9;
+const MAX_COMPENSATION: f64 = 1.2;
+/// Below this charge (in percent) the battery should be swapped before a match.
+pub const LOW_CAPACITY: f64 = 75.0;
+
+/// Returns the factor to multiply output voltages by so motors behave the same
+/// as the battery sags.
+///
+/// Motor voltage commands are a fraction of whatever the battery is supplying,
+/// so a sagging battery makes every open-loop output weaker.
+pub fn compensation() -> f64 {
+    let voltage = battery::voltage();
+    if voltage <= 0.0 {
+        return 1.0;
+    }
+    (NOMINAL_VOLTAGE / voltage).clamp(MIN_COMPENSATION, MAX_COMPENSATION)
+}
+
+/// Returns the battery charge in percent if it is below [`LOW_CAPACITY`].
+pub fn low_capacity() -> Option<f64> {
+    let capacity = battery::capacity();
+    (capacity < LOW_CAPACITY).then_some(capacity)
+}